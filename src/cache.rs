@@ -1,21 +1,14 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 
+/// Lazily generates a value once and shares it, including across threads
 #[derive(Debug, Clone)]
 pub struct Cache<V> {
-	inner: RefCell<Option<Rc<V>>>,
+	inner: OnceLock<Arc<V>>,
 }
 
 impl<V> Cache<V> {
-	pub fn get<F: Fn() -> V>(&self, generator: F) -> Rc<V> {
-		if let Some(existing) = &*self.inner.borrow() {
-			return Rc::clone(existing);
-		}
-
-		let new = Rc::new(generator());
-		self.inner.replace(Some(Rc::clone(&new)));
-
-		new
+	pub fn get<F: Fn() -> V>(&self, generator: F) -> Arc<V> {
+		Arc::clone(self.inner.get_or_init(|| Arc::new(generator())))
 	}
 }
 
@@ -66,4 +59,30 @@ mod tests {
 		assert_eq!(b, c);
 		assert_eq!(generator_count.get(), 1);
 	}
+
+	#[test]
+	fn test_shared_across_threads() {
+		use std::sync::atomic::{AtomicU32, Ordering};
+
+		let cached = Cache::<String>::default();
+		let generator_count = AtomicU32::new(0);
+
+		let values: Vec<_> = std::thread::scope(|scope| {
+			let handles: Vec<_> = (0..4)
+				.map(|_| {
+					scope.spawn(|| {
+						cached.get(|| {
+							generator_count.fetch_add(1, Ordering::SeqCst);
+							String::from("Foobar")
+						})
+					})
+				})
+				.collect();
+
+			handles.into_iter().map(|h| h.join().unwrap()).collect()
+		});
+
+		assert!(values.iter().all(|v| Arc::ptr_eq(v, &values[0])));
+		assert_eq!(generator_count.load(Ordering::SeqCst), 1);
+	}
 }
@@ -1,9 +1,14 @@
-use clap::Parser;
+use crate::download::{ClientOptions, RateLimiter, RetryPolicy, DEFAULT_USER_AGENT};
+use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 #[command(version)]
 pub struct Config {
+	#[command(subcommand)]
+	command: Option<Command>,
+
 	/// Download directory path
 	#[arg(short, long)]
 	destination: PathBuf,
@@ -12,10 +17,18 @@ pub struct Config {
 	#[arg(short, long)]
 	config_file_path: PathBuf,
 
+	/// Path to a JSON file of named secrets that show configs can refer to
+	#[arg(long)]
+	secrets_file: Option<PathBuf>,
+
 	/// Pretend (don't download anything)
 	#[arg(short, long)]
 	pretend: bool,
 
+	/// Use each show's cached feed instead of the network (implies --pretend)
+	#[arg(long)]
+	offline: bool,
+
 	/// Print existing episodes
 	#[arg(short = 'e', long)]
 	print_existing_episodes: bool,
@@ -23,9 +36,87 @@ pub struct Config {
 	/// Limit number of episodes
 	#[arg(short, long)]
 	number_to_download: Option<usize>,
+
+	/// Stop downloading once this many bytes have been (or would be) downloaded this run, e.g. 2G
+	#[arg(long, value_parser = parse_byte_count)]
+	max_bytes: Option<u64>,
+
+	/// Most bytes per second to download, across all episodes, e.g. 500K
+	#[arg(long, value_parser = parse_rate)]
+	limit_rate: Option<u64>,
+
+	/// Number of episodes to download at once
+	#[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+	jobs: u16,
+
+	/// If another run is using the destination, wait for it to finish instead of exiting
+	#[arg(long)]
+	wait_for_lock: bool,
+
+	/// Fail a show if any item in its feed can't be parsed, instead of just warning about it
+	#[arg(long)]
+	strict: bool,
+
+	/// Keep downloading a show's other episodes after one fails
+	#[arg(short, long)]
+	keep_going: bool,
+
+	/// Number of times to retry a failed request
+	#[arg(long, default_value_t = 3)]
+	retries: u32,
+
+	/// Seconds to wait before the first retry (doubling with each one after)
+	#[arg(long, default_value_t = 2.0, value_parser = parse_seconds)]
+	retry_delay: f64,
+
+	/// Longest to wait between retries, in seconds
+	#[arg(long, default_value_t = 60.0, value_parser = parse_seconds)]
+	max_retry_delay: f64,
+
+	/// User-Agent header to send with every request
+	#[arg(long, default_value = DEFAULT_USER_AGENT)]
+	user_agent: String,
+
+	/// Seconds to wait for a connection before giving up on a request
+	#[arg(long, default_value_t = 30.0, value_parser = parse_timeout)]
+	connect_timeout: f64,
+
+	/// Seconds to wait for a stalled server to send anything before giving up on a request
+	#[arg(long, default_value_t = 60.0, value_parser = parse_timeout)]
+	read_timeout: f64,
+
+	/// HTTP or SOCKS proxy URL (by default, taken from the usual *_PROXY environment variables)
+	#[arg(long)]
+	proxy: Option<String>,
+
+	/// PEM file of extra certificate authorities to trust
+	#[arg(long)]
+	ca_bundle: Option<PathBuf>,
+
+	/// Minimum seconds between requests to the same host
+	#[arg(long, default_value_t = 0.5, value_parser = parse_seconds)]
+	host_delay: f64,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+	/// Check archived episodes for empty, truncated, or non-media files instead of downloading
+	Verify {
+		/// Delete bad files (keeping truncated ones to resume) so the next run downloads them again
+		#[arg(long)]
+		requeue: bool,
+	},
 }
 
 impl Config {
+	pub fn command(&self) -> Option<&Command> {
+		self.command.as_ref()
+	}
+
+	pub fn requeue(&self) -> bool {
+		matches!(self.command, Some(Command::Verify { requeue: true }))
+	}
+
 	pub fn destination(&self) -> &Path {
 		&self.destination
 	}
@@ -34,8 +125,17 @@ impl Config {
 		&self.config_file_path
 	}
 
-	pub fn pretend(&self) -> bool {
-		self.pretend
+	pub fn secrets_file(&self) -> Option<&Path> {
+		self.secrets_file.as_deref()
+	}
+
+	pub fn offline(&self) -> bool {
+		self.offline
+	}
+
+	/// Whether we're allowed to actually download episodes (and write to the archive)
+	pub fn may_download(&self) -> bool {
+		!self.pretend && !self.offline
 	}
 
 	pub fn print_existing_episodes(&self) -> bool {
@@ -45,4 +145,138 @@ impl Config {
 	pub fn number_to_download(&self) -> usize {
 		self.number_to_download.unwrap_or(usize::MAX)
 	}
+
+	pub fn max_bytes(&self) -> Option<u64> {
+		self.max_bytes
+	}
+
+	pub fn rate_limiter(&self) -> Option<RateLimiter> {
+		self.limit_rate.map(RateLimiter::new)
+	}
+
+	pub fn jobs(&self) -> usize {
+		self.jobs.into()
+	}
+
+	pub fn wait_for_lock(&self) -> bool {
+		self.wait_for_lock
+	}
+
+	pub fn strict(&self) -> bool {
+		self.strict
+	}
+
+	pub fn keep_going(&self) -> bool {
+		self.keep_going
+	}
+
+	pub fn retry_policy(&self) -> RetryPolicy {
+		RetryPolicy::new(
+			self.retries,
+			Duration::from_secs_f64(self.retry_delay),
+			Duration::from_secs_f64(self.max_retry_delay),
+		)
+	}
+
+	pub fn client_options(&self) -> ClientOptions {
+		ClientOptions {
+			user_agent: self.user_agent.clone(),
+			connect_timeout: Duration::from_secs_f64(self.connect_timeout),
+			read_timeout: Duration::from_secs_f64(self.read_timeout),
+			proxy: self.proxy.clone(),
+			ca_bundle: self.ca_bundle.clone(),
+			host_delay: Duration::from_secs_f64(self.host_delay),
+			connections_per_host: self.jobs(),
+		}
+	}
+}
+
+/// A non-negative number of seconds that fits in a `Duration`
+fn parse_seconds(s: &str) -> Result<f64, String> {
+	match s.trim().parse::<f64>() {
+		Ok(n) if n >= 0.0 && Duration::try_from_secs_f64(n).is_ok() => Ok(n),
+		_ => Err(format!("'{s}' isn't a number of seconds")),
+	}
+}
+
+/// Like `parse_seconds`, but more than zero, which would mean giving up immediately
+fn parse_timeout(s: &str) -> Result<f64, String> {
+	parse_seconds(s).and_then(|n| {
+		if n > 0.0 {
+			Ok(n)
+		} else {
+			Err(format!("'{s}' isn't a timeout longer than zero seconds"))
+		}
+	})
+}
+
+/// A number of bytes with an optional binary suffix, like `750K` or `1.5G`
+fn parse_byte_count(s: &str) -> Result<u64, String> {
+	let s = s.trim();
+	let upper = s.to_ascii_uppercase();
+	let without_unit = upper
+		.strip_suffix("IB")
+		.or_else(|| upper.strip_suffix('B'))
+		.unwrap_or(&upper);
+
+	let (number, multiplier) = match without_unit.chars().last() {
+		Some('K') => (&without_unit[..without_unit.len() - 1], 1u64 << 10),
+		Some('M') => (&without_unit[..without_unit.len() - 1], 1 << 20),
+		Some('G') => (&without_unit[..without_unit.len() - 1], 1 << 30),
+		Some('T') => (&without_unit[..without_unit.len() - 1], 1 << 40),
+		_ => (without_unit, 1),
+	};
+
+	match number.trim().parse::<f64>() {
+		Ok(n) if n.is_finite() && n >= 0.0 => Ok((n * multiplier as f64) as u64),
+		_ => Err(format!(
+			"'{s}' isn't a number of bytes (like 500K, 20M, or 1.5G)"
+		)),
+	}
+}
+
+/// Like `parse_byte_count`, but more than zero, which would mean never downloading anything
+fn parse_rate(s: &str) -> Result<u64, String> {
+	parse_byte_count(s).and_then(|n| {
+		if n > 0 {
+			Ok(n)
+		} else {
+			Err(format!(
+				"'{s}' isn't a rate of at least one byte per second"
+			))
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_byte_count() {
+		assert_eq!(parse_byte_count("1234"), Ok(1234));
+		assert_eq!(parse_byte_count("500K"), Ok(500 * 1024));
+		assert_eq!(parse_byte_count("20mb"), Ok(20 * 1024 * 1024));
+		assert_eq!(parse_byte_count("1.5GiB"), Ok(3 * 512 * 1024 * 1024));
+		assert!(parse_byte_count("lots").is_err());
+		assert!(parse_byte_count("-5M").is_err());
+
+		assert_eq!(parse_rate("500K"), Ok(500 * 1024));
+		assert!(parse_rate("0").is_err());
+		assert!(parse_rate("0.1").is_err());
+	}
+
+	#[test]
+	fn test_parse_seconds() {
+		assert_eq!(parse_seconds("0"), Ok(0.0));
+		assert_eq!(parse_seconds("2.5"), Ok(2.5));
+		assert!(parse_seconds("-1").is_err());
+		assert!(parse_seconds("NaN").is_err());
+		assert!(parse_seconds("inf").is_err());
+		assert!(parse_seconds("soon").is_err());
+
+		assert_eq!(parse_timeout("30"), Ok(30.0));
+		assert!(parse_timeout("0").is_err());
+		assert!(parse_timeout("-30").is_err());
+	}
 }
@@ -0,0 +1,73 @@
+use std::sync::Mutex;
+
+#[derive(Debug, Default)]
+struct BudgetState {
+	used: u64,
+	exhausted: bool,
+}
+
+/// The most a run may download, shared between all of its shows
+#[derive(Debug, Default)]
+pub struct ByteBudget {
+	limit: Option<u64>,
+	state: Mutex<BudgetState>,
+}
+
+impl ByteBudget {
+	pub fn new(limit: Option<u64>) -> Self {
+		Self {
+			limit,
+			state: Mutex::default(),
+		}
+	}
+
+	pub fn is_limited(&self) -> bool {
+		self.limit.is_some()
+	}
+
+	/// Sets aside `estimate` bytes for a download, unless that would go over the limit,
+	/// in which case this (and every later reservation) is refused
+	pub fn reserve(&self, estimate: u64) -> bool {
+		let mut state = self.state.lock().unwrap();
+		let Some(limit) = self.limit else {
+			state.used = state.used.saturating_add(estimate);
+			return true;
+		};
+
+		if !state.exhausted && state.used.saturating_add(estimate) <= limit {
+			state.used += estimate;
+		} else {
+			state.exhausted = true;
+		}
+		!state.exhausted
+	}
+
+	/// Replaces a reservation with what the download actually transferred
+	pub fn settle(&self, estimate: u64, actual: u64) {
+		let mut state = self.state.lock().unwrap();
+		state.used = state.used.saturating_sub(estimate).saturating_add(actual);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_reservations() {
+		let budget = ByteBudget::new(Some(1000));
+
+		assert!(budget.reserve(600));
+		budget.settle(600, 300);
+		assert!(budget.reserve(700));
+		assert!(!budget.reserve(1));
+
+		// Once it's run out, it stays run out
+		budget.settle(700, 0);
+		assert!(!budget.reserve(1));
+
+		let unlimited = ByteBudget::new(None);
+		assert!(unlimited.reserve(u64::MAX));
+		assert!(unlimited.reserve(u64::MAX));
+	}
+}
@@ -0,0 +1,333 @@
+use super::DownloadError;
+use crate::feed::RequestHeaders;
+use crate::filesystem::FilesystemError;
+use crate::interrupt;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_USER_AGENT: &str = concat!("arcast/", env!("CARGO_PKG_VERSION"));
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Everything about how arcast talks to servers that doesn't depend on the show
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+	pub user_agent: String,
+	pub connect_timeout: Duration,
+	pub read_timeout: Duration,
+	pub proxy: Option<String>,
+	pub ca_bundle: Option<PathBuf>,
+	pub host_delay: Duration,
+	pub connections_per_host: usize,
+}
+
+impl Default for ClientOptions {
+	fn default() -> Self {
+		Self {
+			user_agent: DEFAULT_USER_AGENT.into(),
+			connect_timeout: Duration::from_secs(30),
+			read_timeout: Duration::from_secs(60),
+			proxy: None,
+			ca_bundle: None,
+			host_delay: Duration::ZERO,
+			connections_per_host: 1,
+		}
+	}
+}
+
+/// Sends every request arcast makes, reusing connections across the whole run
+#[derive(Debug)]
+pub struct HttpClient {
+	agent: ureq::Agent,
+	host_delay: Duration,
+	next_request_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl HttpClient {
+	pub fn new(options: &ClientOptions) -> Result<Self, Box<DownloadError>> {
+		let proxy = options
+			.proxy
+			.as_deref()
+			.map(|url| {
+				ureq::Proxy::new(url).map_err(|source| {
+					Box::new(DownloadError::Proxy {
+						url: url.into(),
+						source,
+					})
+				})
+			})
+			.transpose()?;
+
+		let tls_config = options
+			.ca_bundle
+			.as_deref()
+			.map(tls_config_trusting)
+			.transpose()?
+			.map(Arc::new);
+
+		let mut builder = ureq::AgentBuilder::new()
+			// Followed by `send` instead, so credentials only go to hosts that are allowed them
+			.redirects(0)
+			.user_agent(&options.user_agent)
+			.timeout_connect(options.connect_timeout)
+			.timeout_read(options.read_timeout)
+			.max_idle_connections_per_host(options.connections_per_host)
+			.try_proxy_from_env(true);
+
+		if let Some(proxy) = proxy {
+			builder = builder.proxy(proxy);
+		}
+		if let Some(tls_config) = tls_config {
+			builder = builder.tls_config(tls_config);
+		}
+
+		Ok(Self {
+			agent: builder.build(),
+			host_delay: options.host_delay,
+			next_request_at: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Sleeps until at least `host_delay` has passed since the last request to `url`'s host
+	fn wait_for_turn(&self, url: &str) {
+		if self.host_delay.is_zero() {
+			return;
+		}
+		let Some(host) = host_of(url) else {
+			return;
+		};
+
+		// Claim the next slot before sleeping, so concurrent downloads from the same host queue up
+		let wait = {
+			let mut next_request_at = self.next_request_at.lock().unwrap();
+			let now = Instant::now();
+			let slot = next_request_at
+				.get(&host)
+				.copied()
+				.filter(|at| *at > now)
+				.unwrap_or(now);
+			next_request_at.insert(host, slot + self.host_delay);
+			slot - now
+		};
+
+		// If we're interrupted, the download stops as soon as the request's made
+		if !wait.is_zero() {
+			interrupt::sleep(wait);
+		}
+	}
+
+	#[allow(clippy::result_large_err)] // This is just ureq's own error type
+	pub(super) fn request(
+		&self,
+		source_url: &str,
+		headers: &[(&str, &str)],
+		credentials: &RequestHeaders,
+	) -> Result<ureq::Response, ureq::Error> {
+		self.send("GET", source_url, headers, credentials)
+	}
+
+	#[allow(clippy::result_large_err)]
+	pub(super) fn head(
+		&self,
+		source_url: &str,
+		headers: &[(&str, &str)],
+		credentials: &RequestHeaders,
+	) -> Result<ureq::Response, ureq::Error> {
+		self.send("HEAD", source_url, headers, credentials)
+	}
+
+	/// Sends `headers` to wherever `source_url` redirects, but `credentials` only to the hosts
+	/// they're for. (ureq would only drop `Authorization`, not API keys and the like.)
+	#[allow(clippy::result_large_err)]
+	fn send(
+		&self,
+		method: &str,
+		source_url: &str,
+		headers: &[(&str, &str)],
+		credentials: &RequestHeaders,
+	) -> Result<ureq::Response, ureq::Error> {
+		let mut url = source_url.to_owned();
+		let mut redirects = 0;
+		loop {
+			self.wait_for_turn(&url);
+
+			let mut req = self.agent.request(method, &url);
+			let credential_headers = credentials
+				.for_url(&url)
+				.iter()
+				.map(|(n, v)| (&n[..], &v[..]));
+			for (name, value) in headers.iter().copied().chain(credential_headers) {
+				req = req.set(name, value);
+			}
+			let resp = req.call()?;
+
+			let Some(location) = redirect_location(&url, &resp) else {
+				return Ok(resp);
+			};
+			if redirects == MAX_REDIRECTS {
+				return Err(ureq::Error::Status(resp.status(), resp));
+			}
+			redirects += 1;
+			url = location;
+		}
+	}
+}
+
+/// Where a redirect response points, resolved against the URL it came from
+fn redirect_location(url: &str, resp: &ureq::Response) -> Option<String> {
+	if !matches!(resp.status(), 301 | 302 | 303 | 307 | 308) {
+		return None;
+	}
+	let location = resp.header("Location")?;
+	let url = url::Url::parse(url).ok()?.join(location).ok()?;
+
+	Some(url.into())
+}
+
+fn host_of(url: &str) -> Option<String> {
+	let url = url::Url::parse(url).ok()?;
+	url.host_str().map(str::to_ascii_lowercase)
+}
+
+/// The usual web roots, plus whatever certificates are in the PEM file at `ca_bundle`
+fn tls_config_trusting(ca_bundle: &Path) -> Result<rustls::ClientConfig, Box<DownloadError>> {
+	let path_string = ca_bundle.to_string_lossy();
+	let certificates_error = |reason: String| DownloadError::Certificates {
+		path: path_string.clone().into_owned(),
+		reason,
+	};
+
+	let file = FilesystemError::handling_io_error_in(path_string.clone(), || {
+		std::fs::File::open(ca_bundle)
+	})?;
+
+	let mut roots = rustls::RootCertStore::empty();
+	roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+	let mut added = 0;
+	for certificate in rustls_pemfile::certs(&mut std::io::BufReader::new(file)) {
+		let certificate = certificate.map_err(|e| certificates_error(e.to_string()))?;
+		roots
+			.add(certificate)
+			.map_err(|e| certificates_error(e.to_string()))?;
+		added += 1;
+	}
+
+	if added == 0 {
+		return Err(Box::new(certificates_error(
+			"no PEM certificates found".into(),
+		)));
+	}
+
+	Ok(rustls::ClientConfig::builder()
+		.with_root_certificates(roots)
+		.with_no_client_auth())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bad_options() {
+		let bad_proxy = ClientOptions {
+			proxy: Some("ftp://proxy.example.com".into()),
+			..ClientOptions::default()
+		};
+		assert!(matches!(
+			HttpClient::new(&bad_proxy).map_err(|e| *e),
+			Err(DownloadError::Proxy { .. })
+		));
+
+		let missing_bundle = ClientOptions {
+			ca_bundle: Some("/nonexistent/arcast-ca.pem".into()),
+			..ClientOptions::default()
+		};
+		assert!(matches!(
+			HttpClient::new(&missing_bundle).map_err(|e| *e),
+			Err(DownloadError::Filesystem(_))
+		));
+	}
+
+	#[test]
+	fn test_host_delay() -> Result<(), Box<DownloadError>> {
+		let client = HttpClient::new(&ClientOptions {
+			host_delay: Duration::from_millis(50),
+			..ClientOptions::default()
+		})?;
+
+		let start = Instant::now();
+		client.wait_for_turn("https://cdn.example.com/1.mp3");
+		client.wait_for_turn("https://other.example.com/feed.xml");
+		assert!(start.elapsed() < Duration::from_millis(50));
+
+		client.wait_for_turn("https://CDN.example.com/2.mp3");
+		assert!(start.elapsed() >= Duration::from_millis(50));
+
+		Ok(())
+	}
+
+	/// Answers one request on a new port with `response`, handing back what was asked
+	fn serve_once(response: String) -> (u16, std::thread::JoinHandle<String>) {
+		use std::io::{BufRead, BufReader, Write};
+
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let server = std::thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream);
+			let mut request = String::new();
+			while reader.read_line(&mut request).unwrap() > 2 && !request.ends_with("\r\n\r\n") {}
+			reader.get_mut().write_all(response.as_bytes()).unwrap();
+			request
+		});
+
+		(port, server)
+	}
+
+	#[test]
+	fn test_redirects_keep_credentials_on_their_host() {
+		let (cdn_port, cdn) = serve_once(
+			"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok".into(),
+		);
+		// Another host as far as the allowlist is concerned, even though it's the same machine
+		let (feed_port, feed) = serve_once(format!(
+			"HTTP/1.1 302 Found\r\nLocation: http://localhost:{cdn_port}/1.mp3\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+		));
+
+		let credentials = RequestHeaders::new(
+			vec![("X-Api-Key".into(), "SECRET123".into())],
+			&["127.0.0.1".into()],
+		);
+		let client = HttpClient::new(&ClientOptions::default()).unwrap();
+		let resp = client
+			.request(
+				&format!("http://127.0.0.1:{feed_port}/1.mp3"),
+				&[("Range", "bytes=0-")],
+				&credentials,
+			)
+			.unwrap();
+		assert_eq!(resp.into_string().unwrap(), "ok");
+
+		let feed_request = feed.join().unwrap().to_ascii_lowercase();
+		let cdn_request = cdn.join().unwrap().to_ascii_lowercase();
+		assert!(feed_request.contains("x-api-key: secret123"));
+		assert!(!cdn_request.contains("secret123"));
+		// Everything else still goes along
+		assert!(cdn_request.contains("range: bytes=0-"));
+	}
+
+	#[test]
+	fn test_empty_ca_bundle() -> Result<(), Box<dyn std::error::Error>> {
+		let path = std::env::temp_dir().join(format!("arcast-empty-ca-{}.pem", std::process::id()));
+		std::fs::write(&path, "not a certificate\n")?;
+
+		let result = tls_config_trusting(&path).map_err(|e| *e);
+		std::fs::remove_file(&path)?;
+
+		assert!(matches!(result, Err(DownloadError::Certificates { .. })));
+		Ok(())
+	}
+}
@@ -0,0 +1,35 @@
+use super::{ByteBudget, HttpClient, RateLimiter, RetryPolicy};
+use getset::Getters;
+
+/// Created once per run and shared by every show and download in it
+#[derive(Debug, Getters)]
+#[get = "pub"]
+pub struct DownloadContext {
+	client: HttpClient,
+	retry_policy: RetryPolicy,
+
+	#[getset(skip)]
+	rate_limiter: Option<RateLimiter>,
+
+	budget: ByteBudget,
+}
+
+impl DownloadContext {
+	pub fn new(
+		client: HttpClient,
+		retry_policy: RetryPolicy,
+		rate_limiter: Option<RateLimiter>,
+		budget: ByteBudget,
+	) -> Self {
+		Self {
+			client,
+			retry_policy,
+			rate_limiter,
+			budget,
+		}
+	}
+
+	pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+		self.rate_limiter.as_ref()
+	}
+}
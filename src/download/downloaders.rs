@@ -1,48 +1,260 @@
 use super::error::*;
 use super::heap_buffer::*;
+use super::{DownloadContext, RateLimiter};
+use crate::feed::RequestHeaders;
 use crate::filesystem::FilesystemError;
-use std::io::{BufWriter, Read, Write};
-use std::path::Path;
+use crate::interrupt;
+use crate::media::{MediaKind, SNIFF_LENGTH};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
 
-pub fn download_to_reader(
+/// ureq already undoes gzip, but some feed hosts still use deflate
+pub(super) fn into_decoded_reader(resp: ureq::Response) -> Box<dyn Read + Send + Sync> {
+	let is_deflated = resp
+		.header("Content-Encoding")
+		.is_some_and(|encoding| encoding.trim().eq_ignore_ascii_case("deflate"));
+
+	let reader = resp.into_reader();
+	if is_deflated {
+		Box::new(flate2::read::ZlibDecoder::new(reader))
+	} else {
+		reader
+	}
+}
+
+fn content_length(resp: &ureq::Response) -> Option<usize> {
+	resp.header("Content-Length").and_then(|s| s.parse().ok())
+}
+
+/// How big the server says `source_url` is, without downloading it
+pub fn remote_size(
+	context: &DownloadContext,
 	source_url: &str,
-) -> Result<(impl Read, Option<usize>), Box<DownloadError>> {
-	let agent = ureq::AgentBuilder::new().redirects(10).build();
-	let resp = agent.get(source_url).call()?;
-	let content_length = resp.header("Content-Length").and_then(|s| s.parse().ok());
-	Ok((resp.into_reader(), content_length))
+	credentials: &RequestHeaders,
+) -> Option<u64> {
+	let resp = context.client().head(source_url, &[], credentials).ok()?;
+
+	content_length(&resp).map(|len| len as u64)
+}
+
+/// Where an in-progress download of `dest_path` lives until it's complete
+pub fn partial_path(dest_path: &Path) -> PathBuf {
+	let mut file_name = dest_path.file_name().unwrap_or_default().to_os_string();
+	file_name.push(".part");
+	dest_path.with_file_name(file_name)
+}
+
+/// Where we keep the validator of the response a `.part` file came from,
+/// so resuming it can make sure the enclosure hasn't changed in the meantime
+fn validator_path(part_path: &Path) -> PathBuf {
+	let mut file_name = part_path.file_name().unwrap_or_default().to_os_string();
+	file_name.push(".validator");
+	part_path.with_file_name(file_name)
 }
 
-pub fn download_to_file<P: AsRef<Path>, PF>(
+/// What to send as `If-Range`: a strong ETag, or failing that, Last-Modified
+fn validator_of(resp: &ureq::Response) -> Option<String> {
+	resp.header("ETag")
+		.filter(|etag| !etag.trim_start().starts_with("W/"))
+		.or_else(|| resp.header("Last-Modified"))
+		.map(|validator| validator.trim().to_owned())
+}
+
+/// Records (or forgets) the validator for a `.part` file that's being started over
+fn save_validator(validator_path: &Path, validator: Option<&str>) -> Result<(), FilesystemError> {
+	let path_string = validator_path.to_string_lossy();
+	FilesystemError::handling_io_error_in(path_string, || {
+		validator.map_or_else(
+			|| remove_if_present(validator_path),
+			|validator| std::fs::write(validator_path, validator),
+		)
+	})
+}
+
+fn remove_if_present(path: &Path) -> std::io::Result<()> {
+	match std::fs::remove_file(path) {
+		Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+/// Whether a response to a request for `bytes={requested_start}-` actually starts there
+fn honors_range(status: u16, content_range: Option<&str>, requested_start: usize) -> bool {
+	if status != 206 {
+		return false;
+	}
+
+	// Content-Range: bytes 1000-1999/2000
+	let start = content_range
+		.and_then(|cr| cr.trim().strip_prefix("bytes "))
+		.and_then(|cr| cr.split('-').next())
+		.and_then(|start| start.trim().parse::<usize>().ok());
+
+	start == Some(requested_start)
+}
+
+/// Downloads to `part_path`, resuming whatever an earlier attempt left there if the server
+/// supports ranges. Once this succeeds the file is complete and synced to disk, ready to be
+/// renamed into place. `transferred` counts the bytes actually received, whether or not it succeeds.
+pub fn download_to_partial_file<PF>(
+	source_url: &str,
+	part_path: &Path,
+	context: &DownloadContext,
+	credentials: &RequestHeaders,
+	transferred: &mut u64,
+	mut progress_func: PF,
+) -> Result<usize, Box<DownloadError>>
+where
+	PF: FnMut(f64),
+{
+	// Every attempt picks up wherever the `.part` file left off
+	context.retry_policy().run(|| {
+		download_attempt(
+			context,
+			source_url,
+			part_path,
+			credentials,
+			transferred,
+			&mut progress_func,
+		)
+	})
+}
+
+fn download_attempt<PF>(
+	context: &DownloadContext,
 	source_url: &str,
-	dest_path: P,
+	part_path: &Path,
+	credentials: &RequestHeaders,
+	transferred: &mut u64,
 	mut progress_func: PF,
 ) -> Result<usize, Box<DownloadError>>
 where
 	PF: FnMut(f64),
 {
-	let dest_path = dest_path.as_ref();
-	let (mut downloader, content_length) = download_to_reader(source_url)?;
+	let client = context.client();
+	let part_path_string = part_path.to_string_lossy();
+	let existing_len = std::fs::metadata(part_path).map_or(0, |m| m.len() as usize);
+	let validator_path = validator_path(part_path);
+
+	let (resp, resume_offset) = if existing_len > 0 {
+		let range = format!("bytes={}-", existing_len);
+		// Without one (say, the server never sent any) there's no way to check, so we just trust it
+		let validator = std::fs::read_to_string(&validator_path).ok();
+		let mut range_headers = vec![("Range", &range[..])];
+		if let Some(validator) = validator.as_deref() {
+			// A changed enclosure gets sent whole, rather than its end appended to the old start
+			range_headers.push(("If-Range", validator.trim()));
+		}
+		match client.request(source_url, &range_headers, credentials) {
+			Ok(resp) if honors_range(resp.status(), resp.header("Content-Range"), existing_len) => {
+				(resp, existing_len)
+			}
+			Ok(resp) if resp.status() == 200 => (resp, 0),
+			// Either the server answered with a range we didn't ask for,
+			// or the partial file is already as long as (or longer than) the whole thing
+			Ok(_) | Err(ureq::Error::Status(416, _)) => {
+				(client.request(source_url, &[], credentials)?, 0)
+			}
+			Err(e) => return Err(e.into()),
+		}
+	} else {
+		(client.request(source_url, &[], credentials)?, 0)
+	};
+
+	if resume_offset == 0 {
+		save_validator(&validator_path, validator_of(&resp).as_deref())?;
+	}
+
+	let total_length = content_length(&resp).map(|len| len + resume_offset);
+	let content_type = resp.header("Content-Type").map(String::from);
+	let mut body = resp.into_reader();
 
-	let mut file = std::fs::File::create(dest_path)
-		.map_err(|e| FilesystemError::from_io_error(e, dest_path.to_string_lossy()))?;
+	// A resumed download's start has already been checked
+	let mut start = Vec::with_capacity(SNIFF_LENGTH);
+	if resume_offset == 0 {
+		body.by_ref()
+			.take(SNIFF_LENGTH as u64)
+			.read_to_end(&mut start)
+			.map_err(DownloadError::Transfer)?;
+		check_media(source_url, &start, content_type.as_deref())?;
+	}
+	let mut downloader = Cursor::new(start).chain(body);
+
+	let mut file = FilesystemError::handling_io_error_in(part_path_string.clone(), || {
+		if resume_offset > 0 {
+			OpenOptions::new().append(true).open(part_path)
+		} else {
+			std::fs::File::create(part_path)
+		}
+	})?;
 
 	fn us_div(num: usize, den: Option<usize>) -> f64 {
 		den.map(|d| (num as f64) / (d as f64)).unwrap_or_default()
 	}
 
-	pipe(
+	let transferred_before = *transferred;
+	let bytes_written = pipe(
 		&mut downloader,
 		&mut file,
-		dest_path.to_string_lossy(),
-		|cur| progress_func(us_div(cur, content_length)),
-	)
+		part_path_string.clone(),
+		context.rate_limiter(),
+		|cur| {
+			*transferred = transferred_before + cur as u64;
+			progress_func(us_div(resume_offset + cur, total_length));
+		},
+	)?;
+
+	let bytes_written = check_complete(source_url, resume_offset + bytes_written, total_length)?;
+
+	// Renaming a file that's still only in the page cache could leave a hole after a power loss
+	FilesystemError::handling_io_error_in(part_path_string, || file.sync_all())?;
+	FilesystemError::handling_io_error_in(validator_path.to_string_lossy(), || {
+		remove_if_present(&validator_path)
+	})?;
+
+	Ok(bytes_written)
+}
+
+/// Some CDNs answer a missing file with a 200 and an HTML page, which we don't want in the archive
+fn check_media(
+	source_url: &str,
+	start: &[u8],
+	content_type: Option<&str>,
+) -> Result<(), Box<DownloadError>> {
+	let kind = MediaKind::sniff(start);
+	if kind.accepts_download(content_type) {
+		return Ok(());
+	}
+
+	Err(Box::new(DownloadError::NotMedia {
+		url: source_url.into(),
+		content_type: content_type.map(String::from),
+		kind,
+	}))
+}
+
+/// Servers sometimes just hang up partway through, which looks like a successful end of the body
+fn check_complete(
+	source_url: &str,
+	received: usize,
+	expected: Option<usize>,
+) -> Result<usize, Box<DownloadError>> {
+	match expected {
+		Some(expected) if received < expected => Err(Box::new(DownloadError::Incomplete {
+			url: source_url.into(),
+			expected: expected as u64,
+			received: received as u64,
+		})),
+		_ => Ok(received),
+	}
 }
 
 fn pipe<R: Read, W: Write, S: Into<String>, PF>(
 	source: &mut R,
 	dest: &mut W,
 	dest_name: S,
+	rate_limiter: Option<&RateLimiter>,
 	mut progress_func: PF,
 ) -> Result<usize, Box<DownloadError>>
 where
@@ -54,23 +266,33 @@ where
 	// 8 KiB is the size of the BufWriter buffer and is also seemingly what we get from ureq's result reader
 	// So I guess this function allocates 16 KiB of buffers?
 
-	FilesystemError::handling_io_error_in(dest_name, || {
-		loop {
-			let bytes_read = source.read(&mut buf)?;
-			if bytes_read == 0 {
-				break;
-			}
-			dest.write_all(&buf[..bytes_read])?;
-			bytes_written += bytes_read;
+	let dest_name = dest_name.into();
+	let write_error = |e| FilesystemError::from_io_error(e, dest_name.clone());
 
-			progress_func(bytes_written);
+	loop {
+		if interrupt::interrupted() {
+			// Whatever we have so far stays in the file, for resuming later
+			dest.flush().map_err(write_error)?;
+			return Err(Box::new(DownloadError::Interrupted));
 		}
 
-		dest.flush()?;
+		let bytes_read = source.read(&mut buf).map_err(DownloadError::Transfer)?;
+		if bytes_read == 0 {
+			break;
+		}
+		dest.write_all(&buf[..bytes_read]).map_err(write_error)?;
+		bytes_written += bytes_read;
 
-		Ok(bytes_written)
-	})
-	.map_err(std::convert::Into::into)
+		progress_func(bytes_written);
+
+		if let Some(limiter) = rate_limiter {
+			limiter.consume(bytes_read);
+		}
+	}
+
+	dest.flush().map_err(write_error)?;
+
+	Ok(bytes_written)
 }
 
 #[cfg(test)]
@@ -84,8 +306,77 @@ mod tests {
 		let mut dest = [0; 8];
 		let mut dest_cur = std::io::Cursor::new(&mut dest[..]);
 
-		pipe(&mut src_cur, &mut dest_cur, "idk", |_| {}).unwrap();
+		pipe(&mut src_cur, &mut dest_cur, "idk", None, |_| {}).unwrap();
 
 		assert_eq!(src, dest);
 	}
+
+	#[test]
+	fn test_check_media() {
+		let url = "https://example.com/ep.mp3";
+
+		assert!(check_media(url, b"ID3\x04\x00", Some("text/plain")).is_ok());
+		assert!(check_media(url, b"\x00\x01\x02", Some("audio/mpeg")).is_ok());
+		assert!(matches!(
+			check_media(url, b"<!DOCTYPE html>", Some("audio/mpeg")).map_err(|e| *e),
+			Err(DownloadError::NotMedia {
+				kind: MediaKind::Markup,
+				..
+			})
+		));
+		assert!(check_media(url, b"Not Found", Some("text/html")).is_err());
+		assert!(check_media(url, b"", None).is_err());
+	}
+
+	#[test]
+	fn test_check_complete() {
+		let url = "https://example.com/ep.mp3";
+
+		assert_eq!(check_complete(url, 100, Some(100)).unwrap(), 100);
+		assert_eq!(check_complete(url, 100, None).unwrap(), 100);
+		assert!(matches!(
+			check_complete(url, 60, Some(100)).map_err(|e| *e),
+			Err(DownloadError::Incomplete {
+				expected: 100,
+				received: 60,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn test_partial_path() {
+		assert_eq!(
+			partial_path(Path::new("/archive/Show - 2021-02-21.mp3")),
+			Path::new("/archive/Show - 2021-02-21.mp3.part")
+		);
+	}
+
+	#[test]
+	fn test_validator_of() {
+		let response = |headers: &str| -> ureq::Response {
+			format!("HTTP/1.1 200 OK\r\n{headers}\r\n").parse().unwrap()
+		};
+		let last_modified = "Last-Modified: Thu, 04 Mar 2021 10:00:00 GMT\r\n";
+
+		assert_eq!(
+			validator_of(&response(&format!("ETag: \"abc\"\r\n{last_modified}"))).as_deref(),
+			Some("\"abc\"")
+		);
+		// Weak ETags aren't allowed in If-Range
+		assert_eq!(
+			validator_of(&response(&format!("ETag: W/\"abc\"\r\n{last_modified}"))).as_deref(),
+			Some("Thu, 04 Mar 2021 10:00:00 GMT")
+		);
+		assert_eq!(validator_of(&response("")), None);
+	}
+
+	#[test]
+	fn test_honors_range() {
+		assert!(honors_range(206, Some("bytes 1000-1999/2000"), 1000));
+		assert!(honors_range(206, Some("bytes 1000-1999/*"), 1000));
+		assert!(!honors_range(206, Some("bytes 0-1999/2000"), 1000));
+		assert!(!honors_range(206, None, 1000));
+		assert!(!honors_range(200, Some("bytes 1000-1999/2000"), 1000));
+	}
 }
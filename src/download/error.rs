@@ -1,13 +1,111 @@
 use crate::filesystem::FilesystemError;
+use crate::media::MediaKind;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::large_enum_variant)] // This is always boxed
 pub enum DownloadError {
 	#[error(transparent)]
-	NetworkConnection(#[from] ureq::Error),
+	NetworkConnection(ureq::Error),
+
+	#[error("{url}: status code {status} (not retrying)")]
+	Gone { url: String, status: u16 },
+
+	#[error("transfer interrupted: {0}")]
+	Transfer(#[source] std::io::Error),
+
+	#[error("{url}: connection closed after {received} of {expected} bytes")]
+	Incomplete {
+		url: String,
+		expected: u64,
+		received: u64,
+	},
+
+	#[error(
+		"{url}: got {kind} instead of audio or video (Content-Type: {})",
+		content_type.as_deref().unwrap_or("none")
+	)]
+	NotMedia {
+		url: String,
+		content_type: Option<String>,
+		kind: MediaKind,
+	},
+
+	#[error("interrupted")]
+	Interrupted,
 
 	#[error(transparent)]
 	Filesystem(#[from] crate::filesystem::FilesystemError),
+
+	#[error("no cached copy of {0} to use offline")]
+	NotCached(String),
+
+	#[error("invalid proxy {url}: {source}")]
+	Proxy {
+		url: String,
+		#[source]
+		source: ureq::Error,
+	},
+
+	#[error("{path}: {reason}")]
+	Certificates { path: String, reason: String },
+}
+
+impl DownloadError {
+	/// Whether trying again later might go differently
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			Self::NetworkConnection(ureq::Error::Status(status, _)) => {
+				matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+			}
+			Self::NetworkConnection(ureq::Error::Transport(_))
+			| Self::Transfer(_)
+			| Self::Incomplete { .. } => true,
+			Self::Gone { .. }
+			| Self::NotMedia { .. }
+			| Self::Interrupted
+			| Self::Filesystem(_)
+			| Self::NotCached(_)
+			| Self::Proxy { .. }
+			| Self::Certificates { .. } => false,
+		}
+	}
+
+	/// How long the server asked us to wait, if it did
+	pub fn retry_after(&self) -> Option<Duration> {
+		match self {
+			Self::NetworkConnection(ureq::Error::Status(429 | 503, resp)) => {
+				parse_retry_after(resp.header("Retry-After")?)
+			}
+			_ => None,
+		}
+	}
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+	let value = value.trim();
+	if let Ok(seconds) = value.parse() {
+		return Some(Duration::from_secs(seconds));
+	}
+
+	let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+	let wait = date.signed_duration_since(chrono::Utc::now());
+
+	Some(wait.to_std().unwrap_or_default())
+}
+
+impl From<ureq::Error> for DownloadError {
+	fn from(src: ureq::Error) -> Self {
+		match src {
+			ureq::Error::Status(status @ (404 | 410), resp) => Self::Gone {
+				url: resp.get_url().into(),
+				status,
+			},
+			src => Self::NetworkConnection(src),
+		}
+	}
 }
 
 impl From<ureq::Error> for Box<DownloadError> {
@@ -21,3 +119,41 @@ impl From<FilesystemError> for Box<DownloadError> {
 		Box::new(From::from(src))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_retry_after() {
+		assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+		assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+		assert_eq!(
+			parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+			Some(Duration::ZERO)
+		);
+		assert_eq!(parse_retry_after("soon"), None);
+	}
+
+	#[test]
+	fn test_status_classification() {
+		let gone: DownloadError =
+			ureq::Error::Status(404, ureq::Response::new(404, "Not Found", "").unwrap()).into();
+		assert!(matches!(gone, DownloadError::Gone { status: 404, .. }));
+		assert!(!gone.is_retryable());
+
+		let busy: DownloadError = ureq::Error::Status(
+			503,
+			"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 7\r\n\r\n"
+				.parse()
+				.unwrap(),
+		)
+		.into();
+		assert!(busy.is_retryable());
+		assert_eq!(busy.retry_after(), Some(Duration::from_secs(7)));
+
+		let forbidden: DownloadError =
+			ureq::Error::Status(403, ureq::Response::new(403, "Forbidden", "").unwrap()).into();
+		assert!(!forbidden.is_retryable());
+	}
+}
@@ -0,0 +1,183 @@
+use super::downloaders::into_decoded_reader;
+use super::{DownloadContext, DownloadError};
+use crate::feed::RequestHeaders;
+use crate::filesystem::{self, FilesystemError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+const FEED_CACHE_FILENAME: &str = ".arcast-feed.xml";
+const FEED_CACHE_INFO_FILENAME: &str = ".arcast-feed.json";
+
+/// What we need to ask the server whether the feed has changed since we last fetched it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FeedCacheInfo {
+	url: String,
+	etag: Option<String>,
+	last_modified: Option<String>,
+	fetched_at: DateTime<Utc>,
+}
+
+impl FeedCacheInfo {
+	fn from_response(url: &str, resp: &ureq::Response) -> Self {
+		Self {
+			url: url.into(),
+			etag: resp.header("ETag").map(Into::into),
+			last_modified: resp.header("Last-Modified").map(Into::into),
+			fetched_at: Utc::now(),
+		}
+	}
+
+	fn conditional_headers(&self) -> Vec<(&'static str, &str)> {
+		let mut headers = Vec::new();
+		if let Some(etag) = &self.etag {
+			headers.push(("If-None-Match", &etag[..]));
+		}
+		if let Some(last_modified) = &self.last_modified {
+			headers.push(("If-Modified-Since", &last_modified[..]));
+		}
+		headers
+	}
+}
+
+/// The last copy of a show's feed, kept alongside its archive
+pub struct FeedCache {
+	body_path: PathBuf,
+	info_path: PathBuf,
+}
+
+impl FeedCache {
+	pub fn new(directory: &Path) -> Self {
+		Self {
+			body_path: directory.join(FEED_CACHE_FILENAME),
+			info_path: directory.join(FEED_CACHE_INFO_FILENAME),
+		}
+	}
+
+	/// Anything unreadable is treated as if there were no cache at all
+	fn load_info(&self, url: &str) -> Option<FeedCacheInfo> {
+		let file = std::fs::File::open(&self.info_path).ok()?;
+		let info: FeedCacheInfo = serde_json::from_reader(std::io::BufReader::new(file)).ok()?;
+
+		(info.url == url && self.body_path.is_file()).then_some(info)
+	}
+
+	fn read_body(&self) -> Result<Vec<u8>, FilesystemError> {
+		FilesystemError::handling_io_error_in(self.body_path.to_string_lossy(), || {
+			std::fs::read(&self.body_path)
+		})
+	}
+
+	fn store(&self, info: &FeedCacheInfo, body: &[u8]) -> Result<(), FilesystemError> {
+		use std::io::Write;
+
+		filesystem::write_atomically(&self.body_path, |writer| writer.write_all(body))?;
+		filesystem::write_atomically(&self.info_path, |writer| {
+			Ok(serde_json::to_writer_pretty(writer, info)?)
+		})
+	}
+}
+
+/// The copy of `url` stored by the last `download_feed`, for when we can't reach the network
+pub fn cached_feed(url: &str, cache: Option<&FeedCache>) -> Result<impl Read, Box<DownloadError>> {
+	let cache = cache
+		.filter(|c| c.load_info(url).is_some())
+		.ok_or_else(|| DownloadError::NotCached(url.into()))?;
+
+	Ok(Cursor::new(cache.read_body()?))
+}
+
+/// Fetches `url`, only transferring the whole feed if it changed since the copy in `cache` was stored
+pub fn download_feed(
+	context: &DownloadContext,
+	url: &str,
+	cache: Option<&FeedCache>,
+	credentials: &RequestHeaders,
+) -> Result<impl Read, Box<DownloadError>> {
+	let cached_info = cache.and_then(|c| c.load_info(url));
+	let mut headers = vec![("Accept-Encoding", "gzip, deflate")];
+	if let Some(info) = &cached_info {
+		headers.extend(info.conditional_headers());
+	}
+
+	let resp = context
+		.retry_policy()
+		.run(|| Ok(context.client().request(url, &headers, credentials)?))?;
+
+	if let (Some(cache), Some(_), 304) = (cache, &cached_info, resp.status()) {
+		return Ok(Cursor::new(cache.read_body()?));
+	}
+
+	let info = FeedCacheInfo::from_response(url, &resp);
+	let mut body = Vec::new();
+	into_decoded_reader(resp)
+		.read_to_end(&mut body)
+		.map_err(DownloadError::Transfer)?;
+
+	if let Some(cache) = cache {
+		cache.store(&info, &body)?;
+	}
+
+	Ok(Cursor::new(body))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_conditional_headers() {
+		let mut info = FeedCacheInfo {
+			url: "https://example.com/feed.rss".into(),
+			etag: Some("\"abc\"".into()),
+			last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+			fetched_at: Utc::now(),
+		};
+
+		assert_eq!(
+			info.conditional_headers(),
+			vec![
+				("If-None-Match", "\"abc\""),
+				("If-Modified-Since", "Wed, 21 Oct 2015 07:28:00 GMT")
+			]
+		);
+
+		info.etag = None;
+		info.last_modified = None;
+		assert!(info.conditional_headers().is_empty());
+	}
+
+	#[test]
+	fn test_cache_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+		let directory =
+			std::env::temp_dir().join(format!("arcast-feed-cache-{}", std::process::id()));
+		std::fs::create_dir_all(&directory)?;
+
+		let cache = FeedCache::new(&directory);
+		let url = "https://example.com/feed.rss";
+		assert!(cache.load_info(url).is_none());
+
+		let info = FeedCacheInfo {
+			url: url.into(),
+			etag: Some("\"abc\"".into()),
+			last_modified: None,
+			fetched_at: Utc::now(),
+		};
+		cache.store(&info, b"<rss/>")?;
+
+		assert_eq!(cache.load_info(url), Some(info));
+		assert!(cache.load_info("https://example.com/other.rss").is_none());
+		assert_eq!(cache.read_body()?, b"<rss/>");
+
+		let mut cached = String::new();
+		cached_feed(url, Some(&cache))?.read_to_string(&mut cached)?;
+		assert_eq!(cached, "<rss/>");
+		assert!(cached_feed("https://example.com/other.rss", Some(&cache)).is_err());
+		assert!(cached_feed(url, None).is_err());
+
+		std::fs::remove_dir_all(&directory)?;
+		Ok(())
+	}
+}
@@ -1,3 +1,12 @@
+mod client;
+pub use client::*;
+
+mod budget;
+pub use budget::*;
+
+mod context;
+pub use context::*;
+
 mod downloaders;
 pub use downloaders::*;
 
@@ -5,3 +14,12 @@ mod error;
 pub use error::DownloadError;
 
 mod heap_buffer;
+
+mod rate_limit;
+pub use rate_limit::*;
+
+mod retry;
+pub use retry::*;
+
+mod feed_cache;
+pub use feed_cache::*;
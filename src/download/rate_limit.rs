@@ -0,0 +1,56 @@
+use crate::interrupt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeps every download in a run, together, under a number of bytes per second
+#[derive(Debug)]
+pub struct RateLimiter {
+	bytes_per_second: u64,
+	caught_up_at: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+	pub fn new(bytes_per_second: u64) -> Self {
+		Self {
+			bytes_per_second: bytes_per_second.max(1),
+			caught_up_at: Mutex::new(None),
+		}
+	}
+
+	/// How long `bytes` take at the limit
+	fn duration_of(&self, bytes: usize) -> Duration {
+		Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64)
+	}
+
+	/// Accounts for `bytes` having just been transferred, sleeping until the limit allows more
+	pub fn consume(&self, bytes: usize) {
+		let wait = {
+			let mut caught_up_at = self.caught_up_at.lock().unwrap();
+			let now = Instant::now();
+			// Time spent idle doesn't turn into a burst later
+			let start = caught_up_at.filter(|at| *at > now).unwrap_or(now);
+			let end = start + self.duration_of(bytes);
+			*caught_up_at = Some(end);
+			end - now
+		};
+
+		// Cut short by an interruption, since the download is about to stop anyway
+		interrupt::sleep(wait);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_consume() {
+		let limiter = RateLimiter::new(100_000);
+		assert_eq!(limiter.duration_of(50_000), Duration::from_millis(500));
+
+		let start = Instant::now();
+		limiter.consume(2_000);
+		limiter.consume(3_000);
+		assert!(start.elapsed() >= Duration::from_millis(50));
+	}
+}
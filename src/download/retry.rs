@@ -0,0 +1,133 @@
+use super::DownloadError;
+use crate::interrupt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+	retries: u32,
+	initial_delay: Duration,
+	max_delay: Duration,
+}
+
+impl RetryPolicy {
+	pub fn new(retries: u32, initial_delay: Duration, max_delay: Duration) -> Self {
+		Self {
+			retries,
+			initial_delay,
+			max_delay,
+		}
+	}
+
+	/// Doubles with every attempt, up to `max_delay`
+	fn backoff(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt);
+		self.initial_delay
+			.checked_mul(factor)
+			.unwrap_or(self.max_delay)
+			.min(self.max_delay)
+	}
+
+	/// Calls `operation` until it succeeds, fails in a way that isn't worth retrying, or we run out of retries
+	pub fn run<T, F>(&self, mut operation: F) -> Result<T, Box<DownloadError>>
+	where
+		F: FnMut() -> Result<T, Box<DownloadError>>,
+	{
+		let mut attempt = 0;
+		loop {
+			match operation() {
+				Err(e)
+					if attempt < self.retries && e.is_retryable() && !interrupt::interrupted() =>
+				{
+					let delay = e
+						.retry_after()
+						.map_or_else(|| self.backoff(attempt), |d| d.min(self.max_delay));
+
+					eprintln!(
+						"{} (retrying in {}s, attempt {} of {})",
+						e,
+						delay.as_secs_f32(),
+						attempt + 1,
+						self.retries
+					);
+
+					if !interrupt::sleep(delay) {
+						return Err(Box::new(DownloadError::Interrupted));
+					}
+					attempt += 1;
+				}
+				result => return result,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::Cell;
+
+	fn transfer_error() -> Box<DownloadError> {
+		Box::new(DownloadError::Transfer(
+			std::io::ErrorKind::ConnectionReset.into(),
+		))
+	}
+
+	#[test]
+	fn test_backoff() {
+		let policy = RetryPolicy::new(10, Duration::from_secs(1), Duration::from_secs(10));
+
+		assert_eq!(policy.backoff(0), Duration::from_secs(1));
+		assert_eq!(policy.backoff(1), Duration::from_secs(2));
+		assert_eq!(policy.backoff(3), Duration::from_secs(8));
+		assert_eq!(policy.backoff(4), Duration::from_secs(10));
+		assert_eq!(policy.backoff(100), Duration::from_secs(10));
+	}
+
+	#[test]
+	fn test_retries_until_success() {
+		let policy = RetryPolicy::new(3, Duration::ZERO, Duration::ZERO);
+		let calls = Cell::new(0);
+
+		let result = policy.run(|| {
+			calls.set(calls.get() + 1);
+			if calls.get() < 3 {
+				Err(transfer_error())
+			} else {
+				Ok(calls.get())
+			}
+		});
+
+		assert_eq!(result.unwrap(), 3);
+	}
+
+	#[test]
+	fn test_gives_up() {
+		let policy = RetryPolicy::new(2, Duration::ZERO, Duration::ZERO);
+		let calls = Cell::new(0);
+
+		let result: Result<(), _> = policy.run(|| {
+			calls.set(calls.get() + 1);
+			Err(transfer_error())
+		});
+
+		assert!(result.is_err());
+		assert_eq!(calls.get(), 3);
+	}
+
+	#[test]
+	fn test_permanent_errors_arent_retried() {
+		let policy = RetryPolicy::new(2, Duration::ZERO, Duration::ZERO);
+		let calls = Cell::new(0);
+
+		let result: Result<(), _> = policy.run(|| {
+			calls.set(calls.get() + 1);
+			Err(Box::new(DownloadError::Gone {
+				url: "https://example.com/gone.mp3".into(),
+				status: 410,
+			}))
+		});
+
+		assert!(result.is_err());
+		assert_eq!(calls.get(), 1);
+	}
+}
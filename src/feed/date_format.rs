@@ -4,6 +4,7 @@ use serde::Deserialize;
 use std::ops::Deref;
 use std::ops::Range;
 use std::str::FromStr;
+use thiserror::Error;
 
 #[derive(Debug, Clone)]
 enum RefOrNot<'a, T> {
@@ -22,48 +23,124 @@ impl<'a, T> Deref for RefOrNot<'a, T> {
 	}
 }
 
+const LONG_MONTH_NAMES: &str =
+	"January|February|March|April|May|June|July|August|September|October|November|December";
+const ABBREVIATED_MONTH_NAMES: &str =
+	r#"(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sept?|Oct|Nov|Dec)\b\.?"#;
+
+/// Day first or month first, with an optional ordinal suffix and comma: "4th July, 2019" or "July 4th, 2019"
+fn month_name_pattern(month_names: &str) -> Regex {
+	let day = r#"(\d{1,2})(?:st|nd|rd|th)?"#;
+	Regex::new(&format!(
+		r#"(?i:{day}\s+({month_names}),?\s+(\d{{4}})|({month_names})\s+{day},?\s+(\d{{4}}))"#
+	))
+	.unwrap()
+}
+
 lazy_static! {
 	static ref AMERICAN_CONVENTIONAL_DATE_FORMAT_REGEX: Regex =
 		Regex::new(r#"(\d{1,2})[\-/](\d{1,2})[\-/](\d{2,4})"#).unwrap();
+	static ref ISO_DATE_FORMAT_REGEX: Regex =
+		Regex::new(r#"\b(\d{4})-(\d{1,2})-(\d{1,2})\b"#).unwrap();
+	static ref EUROPEAN_DATE_FORMAT_REGEX: Regex =
+		Regex::new(r#"(\d{1,2})[\./\-](\d{1,2})[\./\-](\d{2,4})"#).unwrap();
+	static ref LONG_MONTH_NAME_DATE_FORMAT_REGEX: Regex = month_name_pattern(LONG_MONTH_NAMES);
+	static ref ABBREVIATED_MONTH_NAME_DATE_FORMAT_REGEX: Regex =
+		month_name_pattern(ABBREVIATED_MONTH_NAMES);
+	static ref COMPACT_DATE_FORMAT_REGEX: Regex =
+		Regex::new(r#"\b(\d{4})(\d{2})(\d{2})\b"#).unwrap();
+}
+
+#[derive(Error, Debug)]
+pub enum DatePatternError {
+	#[error(transparent)]
+	Regex(#[from] regex::Error),
+
+	#[error("date pattern needs a named group '{0}'")]
+	MissingGroup(&'static str),
+}
+
+/// A user-supplied regex with named groups `year`, `day`, and either `month` or `monthName`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DatePattern(Regex);
+
+impl TryFrom<String> for DatePattern {
+	type Error = DatePatternError;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		let regex = Regex::new(&value)?;
+		let has_group = |name| regex.capture_names().flatten().any(|n| n == name);
+
+		if !has_group("year") {
+			return Err(DatePatternError::MissingGroup("year"));
+		}
+		if !has_group("month") && !has_group("monthName") {
+			return Err(DatePatternError::MissingGroup("month"));
+		}
+		if !has_group("day") {
+			return Err(DatePatternError::MissingGroup("day"));
+		}
+
+		Ok(Self(regex))
+	}
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+impl PartialEq for DatePattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.0.as_str() == other.0.as_str()
+	}
+}
+
+impl Eq for DatePattern {}
+
+#[derive(Debug, Deserialize, Clone, Eq, PartialEq)]
 pub enum DateFormat {
+	/// 7/4/19 or 07-04-2019
 	AmericanConventional,
+	/// 2019-07-04
+	Iso,
+	/// 4.7.19 or 04/07/2019
+	European,
+	/// 4 July 2019 or July 4th, 2019
+	LongMonthName,
+	/// 4 Jul 2019 or Jul. 4th, 2019
+	AbbreviatedMonthName,
+	/// 20190704
+	Compact,
+	/// Anything else, as a regex with named groups: `{ "Pattern": "(?P<day>\\d+)\\.(?P<month>\\d+)\\.(?P<year>\\d+)" }`
+	Pattern(DatePattern),
 }
 
 impl DateFormat {
-	pub fn make_extractor(self, edge_strip_raw_pattern: Option<&str>) -> DateExtractor<'static> {
+	pub fn make_extractor(&self, edge_strip_raw_pattern: Option<&str>) -> DateExtractor<'static> {
 		let composed_pattern = Self::composite_pattern(self.pattern(), edge_strip_raw_pattern);
 
-		DateExtractor::new(self, composed_pattern)
+		DateExtractor::new(self.clone(), composed_pattern)
 	}
 
-	fn pattern(self) -> &'static Regex {
+	fn pattern(&self) -> RefOrNot<'static, Regex> {
 		use DateFormat::*;
-		match self {
+		RefOrNot::Borrowed(match self {
 			AmericanConventional => &AMERICAN_CONVENTIONAL_DATE_FORMAT_REGEX,
-		}
+			Iso => &ISO_DATE_FORMAT_REGEX,
+			European => &EUROPEAN_DATE_FORMAT_REGEX,
+			LongMonthName => &LONG_MONTH_NAME_DATE_FORMAT_REGEX,
+			AbbreviatedMonthName => &ABBREVIATED_MONTH_NAME_DATE_FORMAT_REGEX,
+			Compact => &COMPACT_DATE_FORMAT_REGEX,
+			Pattern(pattern) => return RefOrNot::Owned(pattern.0.clone()),
+		})
 	}
 
 	fn extract_date(
-		self,
-		string: &str,
-		composed_pattern: &RefOrNot<Regex>,
-	) -> Option<(NaiveDate, Range<usize>)> {
-		use DateFormat::*;
-		match self {
-			AmericanConventional => Self::extract_date_american(string, composed_pattern),
-		}
-	}
-
-	fn extract_date_american(
+		&self,
 		string: &str,
 		composed_pattern: &RefOrNot<Regex>,
+		two_digit_year_pivot: Option<i32>,
 	) -> Option<(NaiveDate, Range<usize>)> {
 		let captures = composed_pattern.captures(string)?;
-		if captures.len() != 4 {
-			// The 0th capture is the whole match
+		// Named groups don't care, but the edge strip pattern having groups of its own moves ours
+		if !matches!(self, Self::Pattern(_)) && captures.len() != self.pattern().captures_len() {
 			return None;
 		}
 
@@ -71,33 +148,83 @@ impl DateFormat {
 			cap?.as_str().parse().ok()
 		}
 
-		let month = get_num_from_capture(captures.get(1))?;
-		let day = get_num_from_capture(captures.get(2))?;
-		let mut year = get_num_from_capture(captures.get(3))?;
-		if year < 100 {
-			// 17 -> 2017
-			year += 2000;
-		}
+		use DateFormat::*;
+		let (year, month, day) = match self {
+			AmericanConventional => (
+				get_num_from_capture(captures.get(3))?,
+				get_num_from_capture(captures.get(1))?,
+				get_num_from_capture(captures.get(2))?,
+			),
+			Iso | Compact => (
+				get_num_from_capture(captures.get(1))?,
+				get_num_from_capture(captures.get(2))?,
+				get_num_from_capture(captures.get(3))?,
+			),
+			European => (
+				get_num_from_capture(captures.get(3))?,
+				get_num_from_capture(captures.get(2))?,
+				get_num_from_capture(captures.get(1))?,
+			),
+			LongMonthName | AbbreviatedMonthName => {
+				// Only one side of the alternation will have matched
+				let day_first = captures.get(1).is_some();
+				let (day, month, year) = if day_first { (1, 2, 3) } else { (5, 4, 6) };
+				(
+					get_num_from_capture(captures.get(year))?,
+					month_number(captures.get(month)?.as_str())?,
+					get_num_from_capture(captures.get(day))?,
+				)
+			}
+			Pattern(_) => (
+				get_num_from_capture(captures.name("year"))?,
+				get_num_from_capture(captures.name("month"))
+					.or_else(|| month_number(captures.name("monthName")?.as_str()))?,
+				get_num_from_capture(captures.name("day"))?,
+			),
+		};
 
-		let date = NaiveDate::from_ymd_opt(year, month, day)?;
+		let date = NaiveDate::from_ymd_opt(full_year(year, two_digit_year_pivot), month, day)?;
 		let range = captures.get(0)?.range();
 
 		Some((date, range))
 	}
 
 	fn composite_pattern<'a>(
-		base: &'a Regex,
+		base: RefOrNot<'a, Regex>,
 		edge_strip_raw_pattern: Option<&str>,
 	) -> RefOrNot<'a, Regex> {
-		edge_strip_raw_pattern.map_or(RefOrNot::Borrowed(base), |esrp| {
-			let new_raw_pattern = format!("{}{}{}", esrp, base.as_str(), esrp);
-			let new_pattern = crate::feed::RegexContainer::compile_pattern(&new_raw_pattern);
+		let Some(esrp) = edge_strip_raw_pattern else {
+			return base;
+		};
 
-			RefOrNot::Owned(new_pattern)
-		})
+		// Grouped so an alternation in either one doesn't swallow the rest of the pattern
+		let new_raw_pattern = format!("(?:{esrp})(?:{})(?:{esrp})", base.as_str());
+		let new_pattern = crate::feed::RegexContainer::compile_pattern(&new_raw_pattern);
+
+		RefOrNot::Owned(new_pattern)
 	}
 }
 
+/// 17 -> 2017, and with a pivot of 50, 97 -> 1997 (but 49 -> 2049)
+fn full_year(year: i32, two_digit_year_pivot: Option<i32>) -> i32 {
+	match two_digit_year_pivot {
+		_ if year >= 100 => year,
+		Some(pivot) if year >= pivot => year + 1900,
+		_ => year + 2000,
+	}
+}
+
+/// 1 for "Jan", "jan.", or "January"
+fn month_number(name: &str) -> Option<u32> {
+	const ABBREVIATIONS: [&str; 12] = [
+		"jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+	];
+
+	let abbreviation = name.get(..3)?.to_ascii_lowercase();
+	let index = ABBREVIATIONS.iter().position(|a| *a == abbreviation)?;
+	Some(index as u32 + 1)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -139,12 +266,137 @@ mod tests {
 		assert_eq!(date, NaiveDate::from_ymd_opt(2019, 7, 4).unwrap());
 		assert_eq!(range, 8..24);
 	}
+
+	fn extracted(format: &DateFormat, title: &str) -> Option<(NaiveDate, String)> {
+		let extractor = format.make_extractor(Some(r#"[\-\s]*"#));
+		let (date, range) = extractor.extract_date(title)?;
+		let mut remaining = title.to_owned();
+		remaining.replace_range(range, "");
+		Some((date, remaining))
+	}
+
+	fn custom_format(pattern: &str) -> DateFormat {
+		DateFormat::Pattern(DatePattern::try_from(pattern.to_owned()).unwrap())
+	}
+
+	#[test]
+	fn test_custom_pattern() {
+		let format = custom_format(r#"(?P<day>\d{1,2})\.(?P<month>\d{1,2})\.(?P<year>\d{2})"#);
+		let extractor = format
+			.make_extractor(Some(r#"[\-\s]*"#))
+			.with_two_digit_year_pivot(Some(50));
+
+		let (date, range) = extractor
+			.extract_date("FAKESHOW - 4.7.97 - How to raise the dead")
+			.unwrap();
+		assert_eq!(date, NaiveDate::from_ymd_opt(1997, 7, 4).unwrap());
+		assert_eq!(range, 8..20);
+
+		let (date, _) = extractor
+			.extract_date("FAKESHOW - 4.7.19 - How to raise the dead")
+			.unwrap();
+		assert_eq!(date, NaiveDate::from_ymd_opt(2019, 7, 4).unwrap());
+
+		// Without a pivot, two-digit years are all in the 2000s, like the built-in formats
+		let (date, _) = format
+			.make_extractor(None)
+			.extract_date("FAKESHOW - 4.7.97")
+			.unwrap();
+		assert_eq!(date, NaiveDate::from_ymd_opt(2097, 7, 4).unwrap());
+
+		let format = custom_format(r#"(?P<monthName>[A-Za-z]+) (?P<day>\d+) '(?P<year>\d{2})"#);
+		let (date, _) = format
+			.make_extractor(None)
+			.with_two_digit_year_pivot(Some(30))
+			.extract_date("FAKESHOW - Sept 4 '95")
+			.unwrap();
+		assert_eq!(date, NaiveDate::from_ymd_opt(1995, 9, 4).unwrap());
+
+		// The edge strip pattern goes around the whole pattern, not just its last alternative
+		let format = custom_format(r#"(?P<day>\d{2})\.(?P<month>\d{2})\.(?P<year>\d{4})|TBD"#);
+		assert_eq!(
+			extracted(&format, "FAKESHOW - 04.07.2019 - How to raise the dead"),
+			Some((
+				NaiveDate::from_ymd_opt(2019, 7, 4).unwrap(),
+				"FAKESHOWHow to raise the dead".to_owned()
+			))
+		);
+
+		assert!(matches!(
+			DatePattern::try_from(r#"(?P<year>\d{4})-(?P<month>\d{2})"#.to_owned()),
+			Err(DatePatternError::MissingGroup("day"))
+		));
+		assert!(matches!(
+			DatePattern::try_from(r#"(?P<year>\d{4"#.to_owned()),
+			Err(DatePatternError::Regex(_))
+		));
+	}
+
+	#[test]
+	fn test_other_formats() {
+		let july_4 = NaiveDate::from_ymd_opt(2019, 7, 4).unwrap();
+		let expected = Some((july_4, "FAKESHOWHow to raise the dead".to_owned()));
+
+		let cases = [
+			(
+				DateFormat::Iso,
+				"FAKESHOW - 2019-07-04 - How to raise the dead",
+			),
+			(
+				DateFormat::European,
+				"FAKESHOW - 04.07.2019 - How to raise the dead",
+			),
+			(
+				DateFormat::European,
+				"FAKESHOW - 4/7/19 - How to raise the dead",
+			),
+			(
+				DateFormat::LongMonthName,
+				"FAKESHOW - 4 July 2019 - How to raise the dead",
+			),
+			(
+				DateFormat::LongMonthName,
+				"FAKESHOW - July 4th, 2019 - How to raise the dead",
+			),
+			(
+				DateFormat::AbbreviatedMonthName,
+				"FAKESHOW - 4 Jul 2019 - How to raise the dead",
+			),
+			(
+				DateFormat::AbbreviatedMonthName,
+				"FAKESHOW - jul. 4, 2019 - How to raise the dead",
+			),
+			(
+				DateFormat::Compact,
+				"FAKESHOW - 20190704 - How to raise the dead",
+			),
+		];
+		for (format, title) in cases {
+			assert_eq!(
+				extracted(&format, title),
+				expected,
+				"{:?}: {}",
+				format,
+				title
+			);
+		}
+
+		// Abbreviations shouldn't match the start of a full month name, or vice versa
+		assert!(extracted(&DateFormat::AbbreviatedMonthName, "FAKESHOW - July 4, 2019").is_none());
+		assert!(extracted(&DateFormat::LongMonthName, "FAKESHOW - Jul 4, 2019").is_none());
+		// Nor a compact date part of a longer number
+		assert!(extracted(&DateFormat::Compact, "FAKESHOW - 201907041").is_none());
+		assert!(extracted(&DateFormat::Iso, "FAKESHOW - 12019-07-04").is_none());
+		assert!(extracted(&DateFormat::Iso, "FAKESHOW - 2019-07-041").is_none());
+		assert!(extracted(&DateFormat::Iso, "FAKESHOW - 2019-02-30").is_none());
+	}
 }
 
 #[derive(Debug, Clone)]
 pub struct DateExtractor<'a> {
 	format: DateFormat,
 	composed_pattern: RefOrNot<'a, Regex>,
+	two_digit_year_pivot: Option<i32>,
 }
 
 impl<'a> DateExtractor<'a> {
@@ -152,10 +404,18 @@ impl<'a> DateExtractor<'a> {
 		Self {
 			format,
 			composed_pattern,
+			two_digit_year_pivot: None,
 		}
 	}
 
+	/// Two-digit years at or after `pivot` are taken as 19xx, the rest (and all of them by default) as 20xx
+	pub fn with_two_digit_year_pivot(mut self, pivot: Option<i32>) -> Self {
+		self.two_digit_year_pivot = pivot;
+		self
+	}
+
 	pub fn extract_date(&self, string: &str) -> Option<(NaiveDate, Range<usize>)> {
-		self.format.extract_date(string, &self.composed_pattern)
+		self.format
+			.extract_date(string, &self.composed_pattern, self.two_digit_year_pivot)
 	}
 }
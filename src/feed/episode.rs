@@ -1,4 +1,6 @@
-use super::{error::*, RegexContainer, Show, TitleHandling};
+use super::{
+	error::*, parse_pub_date, DateSource, FilenameFields, RegexContainer, Show, TitleHandling,
+};
 use chrono::prelude::*;
 use getset::Getters;
 use regex::Regex;
@@ -9,6 +11,11 @@ lazy_static! {
 	static ref EDGE_TRIM_REGEX: Regex = Regex::new(r#"^\s+|\s+$"#).unwrap();
 	static ref ENCLOSURE_URL_FILE_EXTENSION_REGEX: Regex =
 		Regex::new(r#"(?i)\.([a-z0-9]+)(?:\?.*?)?$"#).unwrap();
+	static ref ENCLOSURE_URL_BASENAME_REGEX: Regex =
+		Regex::new(r#"(?i)([^/?#]+?)(?:\.[a-z0-9]+)?(?:[?#].*)?$"#).unwrap();
+	static ref LINE_BREAK_TAG_REGEX: Regex =
+		Regex::new(r#"(?i)<br\s*/?>|</(?:p|div|li|h[1-6])\s*>"#).unwrap();
+	static ref MARKUP_TAG_REGEX: Regex = Regex::new(r#"<[^>]*>"#).unwrap();
 	static ref STANDARD_CHARACTER_REPLACEMENT_PAIRS: [(&'static str, &'static str); 1] =
 		[("\u{a0}", " ")]; // nbsp -> regular space
 }
@@ -20,6 +27,13 @@ pub struct Episode {
 	enclosure_url: String,
 	filename: String,
 
+	#[builder(default)]
+	guid: Option<String>,
+
+	/// The size the feed claims the enclosure is, if it gives a plausible one
+	#[builder(default)]
+	enclosure_length: Option<u64>,
+
 	#[getset(skip)]
 	episode_name_range: Range<usize>,
 
@@ -34,14 +48,30 @@ impl Episode {
 		let string_pub_date = rss_item
 			.pub_date()
 			.ok_or(ParsingError::EpisodePubDateMissing)?;
-		let mut pub_date = DateTime::parse_from_rfc2822(string_pub_date)?
-			.naive_local()
-			.date();
-
-		if let Some(date_extractor) = show.date_extractor() {
-			if let Some((date, range)) = date_extractor.extract_date(&title) {
+		let mut pub_date = parse_pub_date(string_pub_date, show.pub_date_format().as_deref())
+			.map_err(|source| ParsingError::EpisodePubDate {
+				value: string_pub_date.into(),
+				source,
+			})?
+			.date_in(show.time_zone());
+
+		if let Some(date_extraction) = show.date_extraction() {
+			let description = rss_item
+				.description()
+				.and_then(Self::description_first_line);
+			let text_of = |source| match source {
+				DateSource::Title => Some(&title[..]),
+				DateSource::EnclosureUrl => rss_item.enclosure().map(rss::Enclosure::url),
+				DateSource::Description => description.as_deref(),
+				DateSource::Guid => rss_item.guid().map(rss::Guid::value),
+			};
+
+			if let Some((date, source, range)) = date_extraction.extract_date(text_of) {
 				pub_date = date;
-				title.to_mut().replace_range(range, "");
+				// Only the title has the date cut out of it
+				if source == DateSource::Title {
+					title.to_mut().replace_range(range, "");
+				}
 			}
 		}
 
@@ -52,19 +82,33 @@ impl Episode {
 			TitleHandling::StripAll => None,
 		};
 
-		let enclosure_url: String = rss_item
+		let enclosure = rss_item
 			.enclosure()
-			.ok_or(ParsingError::EpisodeEnclosureURLMissing)?
-			.url()
-			.into();
+			.ok_or(ParsingError::EpisodeEnclosureURLMissing)?;
+		let enclosure_url: String = enclosure.url().into();
+		// Plenty of feeds put 0 (or nothing at all) here
+		let enclosure_length = enclosure.length().trim().parse().ok().filter(|&l| l > 0);
+
+		let itunes = rss_item.itunes_ext();
+		let fields = FilenameFields {
+			show: show.title(),
+			pub_date,
+			title: title.as_deref(),
+			episode: itunes.and_then(|it| it.episode()),
+			season: itunes.and_then(|it| it.season()),
+			guid: rss_item.guid().map(rss::Guid::value),
+			basename: Self::get_enclosure_basename(&enclosure_url),
+			extension: Self::get_enclosure_extension(&enclosure_url),
+		};
+		let (filename, episode_name_range) = Self::generate_filename(show, &fields);
 
-		let filename_extension = Self::get_enclosure_extension(&enclosure_url);
-		let (filename, episode_name_range) =
-			Self::generate_filename(show, pub_date, title, filename_extension);
+		let guid = rss_item.guid().map(|g| g.value().to_owned());
 
 		Ok(Episode {
 			enclosure_url,
 			filename,
+			guid,
+			enclosure_length,
 			episode_name_range,
 			pub_date,
 		})
@@ -80,35 +124,30 @@ impl Episode {
 		"mp3"
 	}
 
-	fn generate_filename(
-		show: &Show,
-		pub_date: NaiveDate,
-		title: Option<impl AsRef<str>>,
-		extension: &str,
-	) -> (String, Range<usize>) {
-		let filename = match title {
-			Some(title) if !title.as_ref().is_empty() => {
-				format!(
-					"{} - {} - {}.{}",
-					show.title(),
-					Self::formatted_string_for_date(pub_date),
-					title.as_ref(),
-					extension
-				)
-			}
-			_ => {
-				format!(
-					"{} - {}.{}",
-					show.title(),
-					Self::formatted_string_for_date(pub_date),
-					extension
-				)
-			}
-		};
+	fn get_enclosure_basename(url: &str) -> Option<&str> {
+		let captures = (*ENCLOSURE_URL_BASENAME_REGEX).captures(url)?;
+		Some(captures.get(1)?.as_str())
+	}
 
-		let name_end_index = filename.len() - (extension.len() + 1); // +1 for the .
+	/// The first line of text in a description, which is often HTML, so a date further down (like
+	/// an old episode's, in show notes) isn't taken for this one's
+	fn description_first_line(description: &str) -> Option<String> {
+		let text = LINE_BREAK_TAG_REGEX.replace_all(description, "\n");
+		let text = MARKUP_TAG_REGEX.replace_all(&text, "");
+
+		let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+		Some(
+			line.replace("&nbsp;", " ")
+				.replace("&lt;", "<")
+				.replace("&gt;", ">")
+				.replace("&quot;", "\"")
+				.replace("&#39;", "'")
+				.replace("&amp;", "&"),
+		)
+	}
 
-		(filename, 0..name_end_index)
+	fn generate_filename(show: &Show, fields: &FilenameFields) -> (String, Range<usize>) {
+		show.filename_template().render(fields)
 	}
 
 	fn process_raw_title(
@@ -139,11 +178,6 @@ impl Episode {
 
 		processed_title
 	}
-
-	fn formatted_string_for_date(date: NaiveDate) -> impl std::fmt::Display {
-		date.format("%F")
-		// Year-month-day format (ISO 8601). Same as %Y-%m-%d. (https://docs.rs/chrono/0.4.19/chrono/format/strftime/index.html)
-	}
 }
 
 impl Episode {
@@ -160,7 +194,7 @@ impl Episode {
 mod tests {
 	use super::super::{DateFormat, ShowBuilder};
 	use super::*;
-	use crate::feed::{DateExtractionBuilder, TitleHandling};
+	use crate::feed::{ArchiveTimeZone, DateExtractionBuilder, TitleHandling};
 
 	fn new_show(strip_patterns: Vec<&str>, date_format: Option<DateFormat>) -> Show {
 		let de = date_format.map(|form| {
@@ -181,6 +215,24 @@ mod tests {
 			.unwrap()
 	}
 
+	fn new_fields<'a>(
+		show: &'a Show,
+		pub_date: NaiveDate,
+		title: Option<&'a str>,
+		extension: &'a str,
+	) -> FilenameFields<'a> {
+		FilenameFields {
+			show: show.title(),
+			pub_date,
+			title,
+			episode: None,
+			season: None,
+			guid: None,
+			basename: None,
+			extension,
+		}
+	}
+
 	#[test]
 	fn test_basic_stripping() {
 		let show = new_show(vec![], None);
@@ -216,8 +268,10 @@ mod tests {
 
 		let pub_date = NaiveDate::from_ymd_opt(2021, 2, 21).unwrap();
 
-		let (filename, ep_name_range) =
-			Episode::generate_filename(&show, pub_date, Some("This Great Ep!"), "wavefile");
+		let (filename, ep_name_range) = Episode::generate_filename(
+			&show,
+			&new_fields(&show, pub_date, Some("This Great Ep!"), "wavefile"),
+		);
 
 		let ep = EpisodeBuilder::default()
 			.enclosure_url("https://example.com/file.mp3")
@@ -236,7 +290,8 @@ mod tests {
 	fn test_generate_filename_with_missing_title() {
 		let show = new_show(vec![], None);
 		let pub_date = NaiveDate::from_ymd_opt(2021, 2, 21).unwrap();
-		let (filename, _) = Episode::generate_filename(&show, pub_date, None::<&str>, "wavefile");
+		let (filename, _) =
+			Episode::generate_filename(&show, &new_fields(&show, pub_date, None, "wavefile"));
 
 		assert_eq!(filename, "FAKESHOW - 2021-02-21.wavefile");
 	}
@@ -245,7 +300,8 @@ mod tests {
 	fn test_generate_filename_with_empty_title() {
 		let show = new_show(vec![], None);
 		let pub_date = NaiveDate::from_ymd_opt(2021, 2, 21).unwrap();
-		let (filename, _) = Episode::generate_filename(&show, pub_date, Some(""), "wavefile");
+		let (filename, _) =
+			Episode::generate_filename(&show, &new_fields(&show, pub_date, Some(""), "wavefile"));
 
 		assert_eq!(filename, "FAKESHOW - 2021-02-21.wavefile");
 	}
@@ -267,6 +323,191 @@ mod tests {
 		let ep = Episode::new(&show, &item).unwrap();
 
 		assert_eq!(ep.filename(), "FAKESHOW - 2003-01-02 - Full Show.mp3");
+		assert_eq!(ep.enclosure_length(), &None);
+	}
+
+	#[test]
+	fn test_long_month_name_date_extraction() {
+		let show = new_show(vec![], Some(DateFormat::LongMonthName));
+
+		let enclosure = rss::EnclosureBuilder::default()
+			.url("https://example.com/file.mp3")
+			.build();
+
+		let item = rss::ItemBuilder::default()
+			.pub_date(Some("01 Jun 2016 14:31:46 -0700".into()))
+			.title(Some("Full Show - January 2nd, 2003".into()))
+			.enclosure(Some(enclosure))
+			.build();
+
+		let ep = Episode::new(&show, &item).unwrap();
+
+		assert_eq!(ep.filename(), "FAKESHOW - 2003-01-02 - Full Show.mp3");
+	}
+
+	#[test]
+	fn test_date_extraction_fallbacks() {
+		let json = r#"{
+			"source": "EnclosureUrl",
+			"format": { "Pattern": "/(?P<year>\\d{4})/(?P<month>\\d{2})/(?P<day>\\d{2})/" },
+			"fallbacks": [
+				{ "source": "Description", "format": "LongMonthName" },
+				{ "format": "Iso" }
+			]
+		}"#;
+		let show = ShowBuilder::default()
+			.title("FAKESHOW")
+			.url("http://example.com/feed.rss")
+			.date_extraction(Some(serde_json::from_str(json).unwrap()))
+			.build()
+			.unwrap();
+
+		let episode = |url: &str, description: &str| {
+			let enclosure = rss::EnclosureBuilder::default().url(url).build();
+			let item = rss::ItemBuilder::default()
+				.pub_date(Some("01 Jun 2016 14:31:46 -0700".into()))
+				.title(Some("Full Show 2012-05-06".into()))
+				.description(Some(description.into()))
+				.enclosure(Some(enclosure))
+				.build();
+			Episode::new(&show, &item).unwrap()
+		};
+
+		let ep = episode(
+			"https://example.com/2014/03/07/show.mp3",
+			"Aired May 4, 2013",
+		);
+		assert_eq!(
+			ep.filename(),
+			"FAKESHOW - 2014-03-07 - Full Show 2012-05-06.mp3"
+		);
+
+		let ep = episode("https://example.com/show.mp3", "Aired May 4, 2013");
+		assert_eq!(
+			ep.filename(),
+			"FAKESHOW - 2013-05-04 - Full Show 2012-05-06.mp3"
+		);
+
+		let ep = episode(
+			"https://example.com/show.mp3",
+			"<p>Aired <b>May&nbsp;4, 2013</b></p>\n",
+		);
+		assert_eq!(
+			ep.filename(),
+			"FAKESHOW - 2013-05-04 - Full Show 2012-05-06.mp3"
+		);
+
+		// Only the first line counts, so show notes mentioning other episodes don't
+		let ep = episode(
+			"https://example.com/show.mp3",
+			"<p>A rerun</p><p>First aired May 4, 2013</p>",
+		);
+		assert_eq!(ep.filename(), "FAKESHOW - 2012-05-06 - Full Show.mp3");
+
+		let ep = episode("https://example.com/show.mp3", "A rerun");
+		assert_eq!(ep.filename(), "FAKESHOW - 2012-05-06 - Full Show.mp3");
+	}
+
+	#[test]
+	fn test_date_extraction_fallbacks_inherit() {
+		let json = r#"{
+			"format": "Iso",
+			"edgeStripPattern": "[\\-\\s]*",
+			"twoDigitYearPivot": 50,
+			"fallbacks": [{ "format": "European" }]
+		}"#;
+		let show = ShowBuilder::default()
+			.title("FAKESHOW")
+			.url("http://example.com/feed.rss")
+			.date_extraction(Some(serde_json::from_str(json).unwrap()))
+			.build()
+			.unwrap();
+
+		let item = rss::ItemBuilder::default()
+			.pub_date(Some("01 Jun 2016 14:31:46 -0700".into()))
+			.title(Some("Full Show - 4.5.97 - Rerun".into()))
+			.enclosure(Some(
+				rss::EnclosureBuilder::default()
+					.url("https://example.com/file.mp3")
+					.build(),
+			))
+			.build();
+
+		let ep = Episode::new(&show, &item).unwrap();
+		assert_eq!(ep.filename(), "FAKESHOW - 1997-05-04 - Full ShowRerun.mp3");
+	}
+
+	#[test]
+	fn test_time_zone() {
+		let item = rss::ItemBuilder::default()
+			.pub_date(Some("Mon, 01 Mar 2021 23:30:00 -0800".into()))
+			.title(Some("Full Show".into()))
+			.enclosure(Some(
+				rss::EnclosureBuilder::default()
+					.url("https://example.com/file.mp3")
+					.build(),
+			))
+			.build();
+
+		let show = new_show(vec![], None);
+		let ep = Episode::new(&show, &item).unwrap();
+		assert_eq!(ep.filename(), "FAKESHOW - 2021-03-01 - Full Show.mp3");
+
+		let show = new_show(vec![], None)
+			.with_default_time_zone(Some(ArchiveTimeZone::parse("Etc/UTC").unwrap()));
+		let ep = Episode::new(&show, &item).unwrap();
+		assert_eq!(ep.filename(), "FAKESHOW - 2021-03-02 - Full Show.mp3");
+	}
+
+	#[test]
+	fn test_templated_filename() {
+		let show = ShowBuilder::default()
+			.title("FAKESHOW")
+			.url("http://example.com/feed.rss")
+			.filename_template(
+				crate::feed::FilenameTemplate::parse(
+					"{date:%Y}-{episode:3} {title} ({basename}).{ext}",
+				)
+				.unwrap(),
+			)
+			.build()
+			.unwrap();
+
+		let enclosure = rss::EnclosureBuilder::default()
+			.url("https://example.com/eps/fake_episode.m4a?src=rss")
+			.length("31337")
+			.build();
+
+		let itunes = rss::extension::itunes::ITunesItemExtensionBuilder::default()
+			.episode(Some("42".into()))
+			.build();
+
+		let item = rss::ItemBuilder::default()
+			.pub_date(Some("01 Jun 2016 14:31:46 -0700".into()))
+			.title(Some("Full Show".into()))
+			.enclosure(Some(enclosure))
+			.itunes_ext(Some(itunes))
+			.build();
+
+		let ep = Episode::new(&show, &item).unwrap();
+
+		assert_eq!(ep.filename(), "2016-042 Full Show (fake_episode).m4a");
+		assert_eq!(ep.episode_name(), "2016-042 Full Show (fake_episode)");
+		assert_eq!(ep.enclosure_length(), &Some(31337));
+	}
+
+	#[test]
+	fn test_enclosure_basename_extraction() {
+		let pairs = [
+			("https://example.com/file.mkv", Some("file")),
+			("https://example.com/dir/file.mkv?query=thing", Some("file")),
+			("https://example.com/dir/file", Some("file")),
+			("https://example.com/dir/", None),
+		];
+
+		for (url, basename) in pairs {
+			assert_eq!(Episode::get_enclosure_basename(url), basename);
+		}
 	}
 
 	#[test]
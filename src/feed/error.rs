@@ -11,9 +11,15 @@ pub enum ParsingError {
 	#[error("episode missing pubDate")]
 	EpisodePubDateMissing,
 
-	#[error(transparent)]
-	EpisodePubDate(#[from] chrono::ParseError),
+	#[error("couldn't parse pubDate {value:?}: {source}")]
+	EpisodePubDate {
+		value: String,
+		source: chrono::ParseError,
+	},
 
 	#[error("episode missing URL")]
 	EpisodeEnclosureURLMissing,
+
+	#[error("{count} of {total} feed items couldn't be parsed (and --strict is on)")]
+	RejectedItems { count: usize, total: usize },
 }
@@ -0,0 +1,368 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+const DEFAULT_TEMPLATE: &str = "{show} - {date}[ - {title}].{ext}";
+const DEFAULT_DATE_FORMAT: &str = "%F";
+// Year-month-day format (ISO 8601). Same as %Y-%m-%d. (https://docs.rs/chrono/0.4.19/chrono/format/strftime/index.html)
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FilenameTemplateError {
+	#[error("unknown filename template placeholder '{0}'")]
+	UnknownPlaceholder(String),
+
+	#[error("'{placeholder}' doesn't accept the argument '{argument}'")]
+	BadArgument {
+		placeholder: String,
+		argument: String,
+	},
+
+	#[error("unterminated '{0}' in filename template")]
+	Unterminated(char),
+
+	#[error("unexpected '{0}' in filename template")]
+	Unexpected(char),
+
+	#[error("filename templates can't contain directory separators")]
+	DirectorySeparator,
+}
+
+/// The values a `FilenameTemplate` can substitute
+/// Any that are `None` (or empty) cause their enclosing `[...]` group to be omitted
+#[derive(Debug, Clone, Copy)]
+pub struct FilenameFields<'a> {
+	pub show: &'a str,
+	pub pub_date: NaiveDate,
+	pub title: Option<&'a str>,
+	pub episode: Option<&'a str>,
+	pub season: Option<&'a str>,
+	pub guid: Option<&'a str>,
+	pub basename: Option<&'a str>,
+	pub extension: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+	Show,
+	Date(String),
+	Title,
+	Episode(Option<usize>),
+	Season(Option<usize>),
+	Guid,
+	Basename,
+	Extension,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+	Literal(String),
+	Placeholder(Placeholder),
+	Optional(Vec<Segment>),
+}
+
+/// A parsed `filenameTemplate`, like `{show} - {date:%Y%m%d}[ - {title}].{ext}`
+/// `{{`, `}}`, `[[`, and `]]` produce literal braces and brackets
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct FilenameTemplate {
+	segments: Vec<Segment>,
+}
+
+impl Default for FilenameTemplate {
+	fn default() -> Self {
+		Self::parse(DEFAULT_TEMPLATE).expect("the default template should be valid")
+	}
+}
+
+impl TryFrom<String> for FilenameTemplate {
+	type Error = FilenameTemplateError;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		Self::parse(&value)
+	}
+}
+
+/// Whether `format` is valid and only asks for things a date has (no times or zones),
+/// since those would fail when the filename is rendered
+fn formats_dates(format: &str) -> bool {
+	use std::fmt::Write;
+
+	if StrftimeItems::new(format).any(|i| matches!(i, Item::Error)) {
+		return false;
+	}
+	let sample = NaiveDate::from_ymd_opt(2000, 1, 1).expect("a valid date");
+	write!(String::new(), "{}", sample.format(format)).is_ok()
+}
+
+impl FilenameTemplate {
+	pub fn parse(template: &str) -> Result<Self, FilenameTemplateError> {
+		let mut chars = template.chars().peekable();
+		let mut segments = Vec::new();
+		let mut optional: Option<Vec<Segment>> = None;
+		let mut literal = String::new();
+
+		fn flush(literal: &mut String, target: &mut Vec<Segment>) {
+			if !literal.is_empty() {
+				target.push(Segment::Literal(std::mem::take(literal)));
+			}
+		}
+
+		while let Some(c) = chars.next() {
+			match c {
+				'{' | '}' | '[' | ']' if chars.peek() == Some(&c) => {
+					chars.next();
+					literal.push(c);
+				}
+				'{' => {
+					let target = optional.as_mut().unwrap_or(&mut segments);
+					flush(&mut literal, target);
+
+					let mut inside = String::new();
+					loop {
+						match chars.next() {
+							Some('}') => break,
+							Some(c) => inside.push(c),
+							None => return Err(FilenameTemplateError::Unterminated('{')),
+						}
+					}
+
+					target.push(Segment::Placeholder(Self::parse_placeholder(&inside)?));
+				}
+				'[' => {
+					if optional.is_some() {
+						return Err(FilenameTemplateError::Unexpected('['));
+					}
+					flush(&mut literal, &mut segments);
+					optional = Some(Vec::new());
+				}
+				']' => {
+					let mut group = optional
+						.take()
+						.ok_or(FilenameTemplateError::Unexpected(']'))?;
+					flush(&mut literal, &mut group);
+					segments.push(Segment::Optional(group));
+				}
+				'}' => return Err(FilenameTemplateError::Unexpected('}')),
+				'/' => return Err(FilenameTemplateError::DirectorySeparator),
+				c => literal.push(c),
+			}
+		}
+
+		if optional.is_some() {
+			return Err(FilenameTemplateError::Unterminated('['));
+		}
+		flush(&mut literal, &mut segments);
+
+		Ok(Self { segments })
+	}
+
+	fn parse_placeholder(inside: &str) -> Result<Placeholder, FilenameTemplateError> {
+		let (name, argument) = match inside.split_once(':') {
+			Some((name, argument)) => (name, Some(argument)),
+			None => (inside, None),
+		};
+
+		let bad_argument = || FilenameTemplateError::BadArgument {
+			placeholder: name.into(),
+			argument: argument.unwrap_or_default().into(),
+		};
+
+		let parse_width = || -> Result<Option<usize>, FilenameTemplateError> {
+			argument
+				.map(|a| a.parse().map_err(|_| bad_argument()))
+				.transpose()
+		};
+
+		let no_argument = |placeholder: Placeholder| {
+			if argument.is_some() {
+				Err(bad_argument())
+			} else {
+				Ok(placeholder)
+			}
+		};
+
+		match name {
+			"show" => no_argument(Placeholder::Show),
+			"date" => {
+				let format = argument.unwrap_or(DEFAULT_DATE_FORMAT);
+				if format.is_empty() || !formats_dates(format) {
+					return Err(bad_argument());
+				}
+				Ok(Placeholder::Date(format.into()))
+			}
+			"title" => no_argument(Placeholder::Title),
+			"episode" => Ok(Placeholder::Episode(parse_width()?)),
+			"season" => Ok(Placeholder::Season(parse_width()?)),
+			"guid" => no_argument(Placeholder::Guid),
+			"basename" => no_argument(Placeholder::Basename),
+			"ext" => no_argument(Placeholder::Extension),
+			_ => Err(FilenameTemplateError::UnknownPlaceholder(name.into())),
+		}
+	}
+
+	/// Returns the filename and the range of it that names the episode (everything before the extension)
+	pub fn render(&self, fields: &FilenameFields) -> (String, std::ops::Range<usize>) {
+		let mut filename = String::new();
+		for segment in &self.segments {
+			Self::render_segment(segment, fields, &mut filename);
+		}
+
+		let suffix_len = fields.extension.len() + 1; // +1 for the .
+		let name_end_index = if filename.len() > suffix_len
+			&& filename.ends_with(&format!(".{}", fields.extension))
+		{
+			filename.len() - suffix_len
+		} else {
+			filename.len()
+		};
+
+		(filename, 0..name_end_index)
+	}
+
+	/// Returns whether anything was missing
+	fn render_segment(segment: &Segment, fields: &FilenameFields, output: &mut String) -> bool {
+		match segment {
+			Segment::Literal(l) => {
+				output.push_str(l);
+				false
+			}
+			Segment::Placeholder(p) => {
+				let value = Self::value_for(p, fields);
+				let missing = value.as_ref().is_none_or(String::is_empty);
+				if let Some(value) = value {
+					output.push_str(&value.replace('/', "-"));
+				}
+				missing
+			}
+			Segment::Optional(group) => {
+				let mut rendered = String::new();
+				let mut any_missing = false;
+				for segment in group {
+					any_missing |= Self::render_segment(segment, fields, &mut rendered);
+				}
+
+				if !any_missing {
+					output.push_str(&rendered);
+				}
+				false
+			}
+		}
+	}
+
+	fn value_for(placeholder: &Placeholder, fields: &FilenameFields) -> Option<String> {
+		fn padded(value: Option<&str>, width: Option<usize>) -> Option<String> {
+			let value = value?.trim();
+			match (width, value.parse::<u64>()) {
+				(Some(width), Ok(number)) => Some(format!("{:0width$}", number, width = width)),
+				_ => Some(value.into()),
+			}
+		}
+
+		match placeholder {
+			Placeholder::Show => Some(fields.show.into()),
+			Placeholder::Date(format) => Some(fields.pub_date.format(format).to_string()),
+			Placeholder::Title => fields.title.map(Into::into),
+			Placeholder::Episode(width) => padded(fields.episode, *width),
+			Placeholder::Season(width) => padded(fields.season, *width),
+			Placeholder::Guid => fields.guid.map(Into::into),
+			Placeholder::Basename => fields.basename.map(Into::into),
+			Placeholder::Extension => Some(fields.extension.into()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fields(title: Option<&str>) -> FilenameFields<'_> {
+		FilenameFields {
+			show: "FAKESHOW",
+			pub_date: NaiveDate::from_ymd_opt(2021, 2, 21).unwrap(),
+			title,
+			episode: Some("7"),
+			season: None,
+			guid: Some("https://example.com/guid/7"),
+			basename: Some("fake_007"),
+			extension: "mp3",
+		}
+	}
+
+	#[test]
+	fn test_default_template() {
+		let template = FilenameTemplate::default();
+
+		let (filename, range) = template.render(&fields(Some("Great Ep")));
+		assert_eq!(filename, "FAKESHOW - 2021-02-21 - Great Ep.mp3");
+		assert_eq!(range, 0..32);
+
+		let (filename, _) = template.render(&fields(None));
+		assert_eq!(filename, "FAKESHOW - 2021-02-21.mp3");
+
+		let (filename, _) = template.render(&fields(Some("")));
+		assert_eq!(filename, "FAKESHOW - 2021-02-21.mp3");
+	}
+
+	#[test]
+	fn test_custom_template() {
+		let template = FilenameTemplate::parse(
+			"{date:%Y%m%d} [[{episode:3}]][ S{season}] {basename} {guid}.{ext}",
+		)
+		.unwrap();
+
+		let (filename, range) = template.render(&fields(None));
+		assert_eq!(
+			filename,
+			"20210221 [007] fake_007 https:--example.com-guid-7.mp3"
+		);
+		assert_eq!(
+			&filename[range],
+			"20210221 [007] fake_007 https:--example.com-guid-7"
+		);
+	}
+
+	#[test]
+	fn test_template_without_extension() {
+		let template = FilenameTemplate::parse("{show} {title}").unwrap();
+
+		let (filename, range) = template.render(&fields(Some("Ep")));
+		assert_eq!(filename, "FAKESHOW Ep");
+		assert_eq!(range, 0..11);
+	}
+
+	#[test]
+	fn test_bad_templates() {
+		use FilenameTemplateError::*;
+
+		assert_eq!(
+			FilenameTemplate::parse("{nope}"),
+			Err(UnknownPlaceholder("nope".into()))
+		);
+		assert!(matches!(
+			FilenameTemplate::parse("{title:5}"),
+			Err(BadArgument { .. })
+		));
+		assert!(matches!(
+			FilenameTemplate::parse("{episode:x}"),
+			Err(BadArgument { .. })
+		));
+		assert!(matches!(
+			FilenameTemplate::parse("{date:%F %H}"),
+			Err(BadArgument { .. })
+		));
+		assert!(matches!(
+			FilenameTemplate::parse("{date:%Y%z}"),
+			Err(BadArgument { .. })
+		));
+		assert_eq!(FilenameTemplate::parse("{show"), Err(Unterminated('{')));
+		assert_eq!(FilenameTemplate::parse("[{show}"), Err(Unterminated('[')));
+		assert_eq!(FilenameTemplate::parse("[[{show}]"), Err(Unexpected(']')));
+		assert_eq!(FilenameTemplate::parse("[ [{show}]]"), Err(Unexpected('[')));
+		assert_eq!(
+			FilenameTemplate::parse("{show}/{title}"),
+			Err(DirectorySeparator)
+		);
+	}
+}
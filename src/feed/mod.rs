@@ -1,4 +1,5 @@
 mod error;
+pub use error::*;
 
 mod parsing;
 pub use parsing::*;
@@ -9,5 +10,14 @@ pub use episode::*;
 mod show;
 pub use show::*;
 
+mod pub_date;
+pub use pub_date::*;
+
+mod time_zone;
+pub use time_zone::*;
+
 mod date_format;
 pub use date_format::*;
+
+mod filename_template;
+pub use filename_template::*;
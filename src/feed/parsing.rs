@@ -1,14 +1,104 @@
 use super::{error::ParsingError, Episode, Show};
 use rss::Channel;
+use std::fmt::Display;
 use std::io::BufReader;
 use std::io::Read;
 
-pub fn episodes_from_reader(reader: impl Read, show: &Show) -> Result<Vec<Episode>, ParsingError> {
+/// A feed item that couldn't be turned into an episode, and enough to find it in the feed
+#[derive(Debug)]
+pub struct RejectedItem {
+	pub index: usize,
+	pub guid: Option<String>,
+	pub title: Option<String>,
+	pub error: ParsingError,
+}
+
+impl Display for RejectedItem {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "item {}", self.index)?;
+		if let Some(guid) = &self.guid {
+			write!(f, " (GUID {:?})", guid)?;
+		}
+		if let Some(title) = &self.title {
+			write!(f, " \"{}\"", title)?;
+		}
+		write!(f, ": {}", self.error)
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedFeed {
+	pub episodes: Vec<Episode>,
+	pub rejected: Vec<RejectedItem>,
+}
+
+impl ParsedFeed {
+	pub fn item_count(&self) -> usize {
+		self.episodes.len() + self.rejected.len()
+	}
+}
+
+pub fn episodes_from_reader(reader: impl Read, show: &Show) -> Result<ParsedFeed, ParsingError> {
 	let channel = Channel::read_from(BufReader::new(reader))?;
 
-	Ok(channel
-		.into_items()
-		.into_iter()
-		.flat_map(|rss_item| Episode::new(show, &rss_item))
-		.collect())
+	let mut parsed = ParsedFeed::default();
+	for (index, rss_item) in channel.into_items().into_iter().enumerate() {
+		match Episode::new(show, &rss_item) {
+			Ok(episode) => parsed.episodes.push(episode),
+			Err(error) => parsed.rejected.push(RejectedItem {
+				index,
+				guid: rss_item.guid().map(|g| g.value().to_owned()),
+				title: rss_item.title().map(String::from),
+				error,
+			}),
+		}
+	}
+
+	Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::feed::ShowBuilder;
+
+	#[test]
+	fn test_rejected_items() -> Result<(), Box<dyn std::error::Error>> {
+		let show = ShowBuilder::default()
+			.title("FAKESHOW")
+			.url("http://example.com/feed.rss")
+			.build()?;
+
+		let feed = r#"<?xml version="1.0"?>
+			<rss version="2.0"><channel><title>FAKESHOW</title><link>http://example.com</link><description/>
+				<item>
+					<title>Good</title>
+					<pubDate>Tue, 01 Jun 2021 14:31:46 -0700</pubDate>
+					<enclosure url="https://example.com/good.mp3" length="0" type="audio/mpeg"/>
+				</item>
+				<item>
+					<title>No Date</title>
+					<guid>no-date</guid>
+					<enclosure url="https://example.com/no-date.mp3" length="0" type="audio/mpeg"/>
+				</item>
+				<item>
+					<pubDate>Wed, 02 Jun 2021 14:31:46 -0700</pubDate>
+				</item>
+			</channel></rss>"#;
+
+		let parsed = episodes_from_reader(feed.as_bytes(), &show)?;
+		assert_eq!(parsed.episodes.len(), 1);
+		assert_eq!(parsed.item_count(), 3);
+
+		let descriptions: Vec<String> = parsed.rejected.iter().map(ToString::to_string).collect();
+		assert_eq!(
+			descriptions,
+			vec![
+				"item 1 (GUID \"no-date\") \"No Date\": episode missing pubDate",
+				"item 2: episode missing title",
+			]
+		);
+
+		Ok(())
+	}
 }
@@ -0,0 +1,231 @@
+use super::ArchiveTimeZone;
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::*;
+use regex::Regex;
+
+lazy_static! {
+	// "Thu, ", "Thurs, ", or "Thursday " at the start, which we don't need and feeds often get wrong
+	static ref LEADING_WEEKDAY_REGEX: Regex =
+		Regex::new(r#"(?i)^(?:mon|tue|wed|thu|fri|sat|sun)[a-z]*\.?,?\s+"#).unwrap();
+	static ref LONG_MONTH_REGEX: Regex = Regex::new(
+		r#"(?i)\b(jan)uary|\b(feb)ruary|\b(mar)ch|\b(apr)il|\b(jun)e|\b(jul)y|\b(aug)ust|\b(sep)t(?:ember)?|\b(oct)ober|\b(nov)ember|\b(dec)ember"#
+	)
+	.unwrap();
+	static ref TRAILING_ZONE_REGEX: Regex = Regex::new(r#"(?i)\s+(utc|z|[a-z]{2,3}t)$"#).unwrap();
+}
+
+/// US zones (which RFC 822 allowed) and the handful of others feeds actually use
+fn zone_offset(name: &str) -> Option<&'static str> {
+	Some(match &name.to_ascii_uppercase()[..] {
+		"UT" | "UTC" | "GMT" | "Z" => "+0000",
+		"EST" => "-0500",
+		"EDT" => "-0400",
+		"CST" => "-0600",
+		"CDT" => "-0500",
+		"MST" => "-0700",
+		"MDT" => "-0600",
+		"PST" => "-0800",
+		"PDT" => "-0700",
+		"AKST" => "-0900",
+		"AKDT" => "-0800",
+		"HST" => "-1000",
+		"BST" => "+0100",
+		"CET" => "+0100",
+		"CEST" => "+0200",
+		_ => return None,
+	})
+}
+
+/// A parsed `pubDate`, which might not say what zone it's in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PubDate {
+	Zoned(DateTime<FixedOffset>),
+	/// Already the publisher's local time, so there's nothing to convert
+	Naive(NaiveDateTime),
+}
+
+impl PubDate {
+	/// The calendar date, reckoned in `time_zone` when there is one and we know where we're
+	/// converting from
+	pub fn date_in(&self, time_zone: Option<ArchiveTimeZone>) -> NaiveDate {
+		match self {
+			Self::Zoned(date_time) => time_zone.map_or_else(
+				|| date_time.naive_local().date(),
+				|time_zone| time_zone.date_of(date_time),
+			),
+			Self::Naive(date_time) => date_time.date(),
+		}
+	}
+}
+
+impl From<DateTime<FixedOffset>> for PubDate {
+	fn from(date_time: DateTime<FixedOffset>) -> Self {
+		Self::Zoned(date_time)
+	}
+}
+
+/// Whether `format` is something chrono can parse with
+pub fn is_valid_format(format: &str) -> bool {
+	!StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
+/// Parses with a strftime `format`, which might only have a date, or no offset
+fn parse_with_format(value: &str, format: &str) -> Option<PubDate> {
+	DateTime::parse_from_str(value, format)
+		.ok()
+		.map(PubDate::Zoned)
+		.or_else(|| {
+			NaiveDateTime::parse_from_str(value, format)
+				.ok()
+				.map(PubDate::Naive)
+		})
+		.or_else(|| {
+			let naive = NaiveDate::parse_from_str(value, format).ok()?;
+			Some(PubDate::Naive(naive.and_hms_opt(0, 0, 0)?))
+		})
+}
+
+/// Cleans up the ways feeds usually break RFC 822: wrong or misspelled weekdays,
+/// spelled-out months, and zone names chrono doesn't know
+fn normalized_rfc2822(value: &str) -> String {
+	let without_weekday = LEADING_WEEKDAY_REGEX.replace(value.trim(), "");
+	let short_months =
+		LONG_MONTH_REGEX.replace_all(&without_weekday, |captures: &regex::Captures| {
+			captures
+				.iter()
+				.skip(1)
+				.flatten()
+				.next()
+				.map_or_else(String::new, |m| m.as_str().to_owned())
+		});
+
+	TRAILING_ZONE_REGEX
+		.replace(&short_months, |captures: &regex::Captures| {
+			zone_offset(&captures[1])
+				.map_or_else(|| captures[0].to_owned(), |offset| format!(" {offset}"))
+		})
+		.into_owned()
+}
+
+/// Everything we've seen in the wild, in rough order of how common it is
+const FALLBACK_FORMATS: [&str; 6] = [
+	"%d %b %Y %H:%M:%S %z",
+	"%d %b %Y %H:%M %z",
+	"%Y-%m-%d %H:%M:%S %z",
+	"%Y-%m-%d %H:%M:%S",
+	"%Y-%m-%dT%H:%M:%S",
+	"%Y-%m-%d",
+];
+
+/// Parses a `pubDate`, trying `custom_format` first, then RFC 2822 (which it's supposed to be),
+/// and then a series of increasingly forgiving fallbacks
+pub fn parse_pub_date(
+	value: &str,
+	custom_format: Option<&str>,
+) -> Result<PubDate, chrono::ParseError> {
+	let value = value.trim();
+
+	if let Some(date) = custom_format.and_then(|format| parse_with_format(value, format)) {
+		return Ok(date);
+	}
+
+	let strict_error = match DateTime::parse_from_rfc2822(value) {
+		Ok(date) => return Ok(date.into()),
+		Err(e) => e,
+	};
+
+	if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+		return Ok(date.into());
+	}
+
+	let normalized = normalized_rfc2822(value);
+	if let Ok(date) = DateTime::parse_from_rfc2822(&normalized) {
+		return Ok(date.into());
+	}
+
+	FALLBACK_FORMATS
+		.iter()
+		.find_map(|format| parse_with_format(&normalized, format))
+		.ok_or(strict_error)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn described(date: PubDate) -> String {
+		match date {
+			PubDate::Zoned(date_time) => date_time.to_rfc3339(),
+			PubDate::Naive(date_time) => date_time.to_string(),
+		}
+	}
+
+	fn parsed(value: &str) -> Option<String> {
+		parse_pub_date(value, None).ok().map(described)
+	}
+
+	#[test]
+	fn test_lenient_parsing() {
+		assert_eq!(
+			parsed("Thu, 04 Mar 2021 10:00:00 +0000").as_deref(),
+			Some("2021-03-04T10:00:00+00:00")
+		);
+		assert_eq!(
+			parsed("2021-03-04T10:00:00Z").as_deref(),
+			Some("2021-03-04T10:00:00+00:00")
+		);
+		assert_eq!(
+			parsed("Thu, 4 Mar 2021 10:00 EST").as_deref(),
+			Some("2021-03-04T10:00:00-05:00")
+		);
+		// Wrong weekday
+		assert_eq!(
+			parsed("Mon, 04 Mar 2021 10:00:00 GMT").as_deref(),
+			Some("2021-03-04T10:00:00+00:00")
+		);
+		assert_eq!(
+			parsed("Thursday, 4 March 2021 10:00:00 PST").as_deref(),
+			Some("2021-03-04T10:00:00-08:00")
+		);
+		assert_eq!(
+			parsed("04 Mar 2021 10:00:00 UTC").as_deref(),
+			Some("2021-03-04T10:00:00+00:00")
+		);
+		assert_eq!(
+			parsed("2021-03-04 10:00:00").as_deref(),
+			Some("2021-03-04 10:00:00")
+		);
+		assert_eq!(parsed("2021-03-04").as_deref(), Some("2021-03-04 00:00:00"));
+		assert!(parsed("sometime last week").is_none());
+	}
+
+	#[test]
+	fn test_custom_format() {
+		let date = parse_pub_date("04.03.2021", Some("%d.%m.%Y")).unwrap();
+		assert_eq!(described(date), "2021-03-04 00:00:00");
+
+		// A custom format that doesn't match still falls back to the usual ones
+		let date = parse_pub_date("2021-03-04T10:00:00Z", Some("%d.%m.%Y")).unwrap();
+		assert_eq!(described(date), "2021-03-04T10:00:00+00:00");
+
+		assert!(is_valid_format("%d.%m.%Y %H:%M"));
+		assert!(!is_valid_format("%d.%m.%Y %Q"));
+	}
+
+	#[test]
+	fn test_date_in() {
+		let los_angeles = ArchiveTimeZone::parse("America/Los_Angeles").ok();
+		let march_4 = NaiveDate::from_ymd_opt(2021, 3, 4).unwrap();
+
+		// Without a zone to convert from, the date is just what the feed says
+		let date = parse_pub_date("2021-03-04", None).unwrap();
+		assert_eq!(date.date_in(None), march_4);
+		assert_eq!(date.date_in(los_angeles), march_4);
+		let date = parse_pub_date("04.03.2021 23:30", Some("%d.%m.%Y %H:%M")).unwrap();
+		assert_eq!(date.date_in(los_angeles), march_4);
+
+		let date = parse_pub_date("Thu, 04 Mar 2021 05:00:00 +0000", None).unwrap();
+		assert_eq!(date.date_in(None), march_4);
+		assert_eq!(date.date_in(los_angeles), march_4.pred_opt().unwrap());
+	}
+}
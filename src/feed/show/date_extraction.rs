@@ -2,10 +2,22 @@ use crate::{
 	cache::Cache,
 	feed::{DateExtractor, DateFormat},
 };
+use chrono::NaiveDate;
 use derive_builder::Builder;
 use getset::Getters;
-use serde::Deserialize;
-use std::rc::Rc;
+use serde::{de, Deserialize, Deserializer};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Which part of a feed item to look for a date in
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateSource {
+	#[default]
+	Title,
+	EnclosureUrl,
+	Description,
+	Guid,
+}
 
 #[derive(Deserialize, Clone, Debug, Getters, Builder)]
 #[builder(setter(into), pattern = "owned")]
@@ -13,20 +25,88 @@ use std::rc::Rc;
 pub struct DateExtraction {
 	format: DateFormat,
 
+	#[serde(default)]
+	#[builder(default)]
+	source: DateSource,
+
+	/// Tried in order when this one doesn't find a date, using this one's edge strip pattern and
+	/// pivot unless they have their own
+	#[serde(default)]
+	#[builder(default)]
+	fallbacks: Vec<DateExtraction>,
+
 	#[getset(skip)]
 	#[serde(rename = "edgeStripPattern")]
 	#[builder(default, setter(into, strip_option))]
 	edge_strip_pattern_raw: Option<String>,
 
+	#[serde(
+		rename = "twoDigitYearPivot",
+		default,
+		deserialize_with = "deserialize_pivot"
+	)]
+	#[builder(default, setter(into, strip_option))]
+	two_digit_year_pivot: Option<i32>,
+
 	#[serde(skip, default)]
 	#[getset(skip)]
 	#[builder(default)]
 	date_extractor: Cache<DateExtractor<'static>>,
 }
 
+/// A pivot past 100 would put every two-digit year in the 2000s, which is surely a mistake
+fn deserialize_pivot<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+	match Option::<i32>::deserialize(deserializer)? {
+		Some(pivot) if !(0..=100).contains(&pivot) => Err(de::Error::custom(format!(
+			"twoDigitYearPivot {pivot} isn't from 0 to 100"
+		))),
+		pivot => Ok(pivot),
+	}
+}
+
 impl DateExtraction {
-	pub fn date_extractor(&self) -> Rc<DateExtractor<'static>> {
-		let esrp = self.edge_strip_pattern_raw.as_ref().map(|s| &s[..]);
-		self.date_extractor.get(|| self.format.make_extractor(esrp))
+	/// `esrp` and `pivot` are what's left after inheriting. A fallback always has the same parent,
+	/// so they're the same every time and can be cached along with the rest.
+	fn date_extractor(
+		&self,
+		esrp: Option<&str>,
+		pivot: Option<i32>,
+	) -> Arc<DateExtractor<'static>> {
+		self.date_extractor.get(|| {
+			self.format
+				.make_extractor(esrp)
+				.with_two_digit_year_pivot(pivot)
+		})
+	}
+
+	/// The first date found by this extraction or one of its fallbacks,
+	/// with the source it came from and where in that source it was
+	pub fn extract_date<'s>(
+		&self,
+		text_of: impl Fn(DateSource) -> Option<&'s str> + Copy,
+	) -> Option<(NaiveDate, DateSource, Range<usize>)> {
+		self.inheriting_extract_date(text_of, None, None)
+	}
+
+	fn inheriting_extract_date<'s>(
+		&self,
+		text_of: impl Fn(DateSource) -> Option<&'s str> + Copy,
+		edge_strip_pattern_raw: Option<&str>,
+		two_digit_year_pivot: Option<i32>,
+	) -> Option<(NaiveDate, DateSource, Range<usize>)> {
+		let esrp = self
+			.edge_strip_pattern_raw
+			.as_deref()
+			.or(edge_strip_pattern_raw);
+		let pivot = self.two_digit_year_pivot.or(two_digit_year_pivot);
+
+		text_of(self.source)
+			.and_then(|text| self.date_extractor(esrp, pivot).extract_date(text))
+			.map(|(date, range)| (date, self.source, range))
+			.or_else(|| {
+				self.fallbacks
+					.iter()
+					.find_map(|fallback| fallback.inheriting_extract_date(text_of, esrp, pivot))
+			})
 	}
 }
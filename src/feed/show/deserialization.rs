@@ -1,14 +1,23 @@
 use super::{Clusions, Show, ShowBuilder, TitleHandling};
+use crate::feed::{is_valid_format, FilenameTemplate};
 use serde::{de, de::Visitor, Deserialize};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Debug)]
 #[serde(field_identifier, rename_all = "camelCase")]
 enum Field {
 	Title,
 	Url,
+	Destination,
+	Auth,
+	Headers,
+	AuthHosts,
+	PubDateFormat,
+	Timezone,
 	DateExtraction,
 	StripWholeTitle,
 	TitleStripPatterns,
+	FilenameTemplate,
 	InclusionPatterns,
 	ExclusionPatterns,
 	NotBefore,
@@ -64,6 +73,30 @@ impl<'de> Visitor<'de> for ShowVisitor {
 				Field::Url => {
 					show_builder.url(map.next_value::<String>()?);
 				}
+				Field::Destination => {
+					show_builder.destination(map.next_value::<Option<_>>()?);
+				}
+				Field::Auth => {
+					show_builder.auth(map.next_value::<Option<_>>()?);
+				}
+				Field::Headers => {
+					show_builder.headers(map.next_value::<BTreeMap<_, _>>()?);
+				}
+				Field::AuthHosts => {
+					show_builder.auth_hosts(map.next_value::<Vec<String>>()?);
+				}
+				Field::PubDateFormat => {
+					let format = map.next_value::<String>()?;
+					if !is_valid_format(&format) {
+						return Err(de::Error::custom(format!(
+							"'{format}' isn't a valid pubDateFormat"
+						)));
+					}
+					show_builder.pub_date_format(Some(format));
+				}
+				Field::Timezone => {
+					show_builder.time_zone(map.next_value::<Option<_>>()?);
+				}
 				Field::DateExtraction => {
 					show_builder.date_extraction(map.next_value::<Option<_>>()?);
 				}
@@ -84,6 +117,9 @@ impl<'de> Visitor<'de> for ShowVisitor {
 					)?;
 					show_builder.title_handling(TitleHandling::StripPatterns(map.next_value()?));
 				}
+				Field::FilenameTemplate => {
+					show_builder.filename_template(map.next_value::<FilenameTemplate>()?);
+				}
 				Field::NotBefore => {
 					show_builder.not_before_date(map.next_value::<Option<_>>()?);
 				}
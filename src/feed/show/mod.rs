@@ -9,6 +9,9 @@ mod deserialization;
 mod regex_container;
 pub use regex_container::*;
 
+mod request_options;
+pub use request_options::*;
+
 #[allow(clippy::module_inception)]
 mod show;
 pub use show::*;
@@ -0,0 +1,87 @@
+use crate::secrets::{SecretError, SecretValue, Secrets};
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// How to identify ourselves to a feed host that wants credentials
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Auth {
+	Basic {
+		username: SecretValue,
+		password: SecretValue,
+	},
+	Bearer(SecretValue),
+}
+
+impl Auth {
+	fn header_value(&self, secrets: &Secrets) -> Result<String, SecretError> {
+		match self {
+			Self::Basic { username, password } => {
+				let credentials = format!(
+					"{}:{}",
+					username.resolve(secrets)?,
+					password.resolve(secrets)?
+				);
+				Ok(format!(
+					"Basic {}",
+					base64::engine::general_purpose::STANDARD.encode(credentials)
+				))
+			}
+			Self::Bearer(token) => Ok(format!("Bearer {}", token.resolve(secrets)?)),
+		}
+	}
+}
+
+/// A show's headers, secrets included, and the hosts that are allowed to see them
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RequestHeaders {
+	headers: Vec<(String, String)>,
+	hosts: Vec<String>,
+	any_host: bool,
+}
+
+impl RequestHeaders {
+	/// `hosts` can include `*` for any host at all
+	pub fn new(headers: Vec<(String, String)>, hosts: &[String]) -> Self {
+		Self {
+			headers,
+			hosts: hosts.iter().map(|host| host.to_ascii_lowercase()).collect(),
+			any_host: hosts.iter().any(|host| host == "*"),
+		}
+	}
+
+	/// What to send with a request for `url`, which is nothing for hosts we weren't told to trust,
+	/// so credentials for the feed don't leak to whatever CDN hosts the episodes
+	pub fn for_url(&self, url: &str) -> &[(String, String)] {
+		let host = url::Url::parse(url)
+			.ok()
+			.and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+		let trusted = self.any_host || host.is_some_and(|host| self.hosts.contains(&host));
+
+		if trusted {
+			&self.headers
+		} else {
+			&[]
+		}
+	}
+}
+
+/// Every header to send with requests for the feed (and any `authHosts`), secrets included
+pub fn request_headers(
+	auth: Option<&Auth>,
+	headers: &BTreeMap<String, SecretValue>,
+	secrets: &Secrets,
+) -> Result<Vec<(String, String)>, SecretError> {
+	let mut resolved = headers
+		.iter()
+		.map(|(name, value)| Ok((name.clone(), value.resolve(secrets)?)))
+		.collect::<Result<Vec<_>, SecretError>>()?;
+
+	if let Some(auth) = auth {
+		resolved.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
+		resolved.push(("Authorization".into(), auth.header_value(secrets)?));
+	}
+
+	Ok(resolved)
+}
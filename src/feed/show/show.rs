@@ -1,9 +1,15 @@
-use super::{Clusions, DateExtraction, RegexContainer, TitleHandling};
-use crate::{cache::Cache, feed::DateExtractor};
+use super::{Auth, Clusions, DateExtraction, RegexContainer, RequestHeaders, TitleHandling};
+use crate::{
+	cache::Cache,
+	feed::{ArchiveTimeZone, FilenameTemplate},
+	secrets::{SecretError, SecretValue, Secrets},
+};
 use chrono::NaiveDate;
 use derive_builder::Builder;
 use getset::{CopyGetters, Getters};
-use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Debug, Getters, CopyGetters, Builder)]
 #[builder(setter(into))]
@@ -12,13 +18,39 @@ pub struct Show {
 	title: String,
 	url: String,
 
+	#[builder(default)]
+	destination: Option<PathBuf>,
+
+	#[builder(default)]
+	auth: Option<Auth>,
+
+	#[builder(default)]
+	headers: BTreeMap<String, SecretValue>,
+
+	/// Hosts besides the feed's that get `auth` and `headers` (`*` for all of them)
+	#[builder(default)]
+	auth_hosts: Vec<String>,
+
 	#[builder(default)]
 	title_handling: TitleHandling,
 
+	#[builder(default)]
+	filename_template: FilenameTemplate,
+
 	#[builder(default)]
 	#[getset(skip)]
 	regex_container: Cache<RegexContainer>,
 
+	/// A strftime format to try before the usual ones, for feeds with unusual `pubDate`s
+	#[builder(default)]
+	pub_date_format: Option<String>,
+
+	/// Which zone's calendar decides an episode's date, instead of whatever offset the publisher used
+	#[builder(default)]
+	#[getset(skip)]
+	#[get_copy = "pub"]
+	time_zone: Option<ArchiveTimeZone>,
+
 	#[builder(default)]
 	date_extraction: Option<DateExtraction>,
 
@@ -37,14 +69,28 @@ impl Show {
 		self.title_handling().strip_patterns()
 	}
 
-	pub fn regex_container(&self) -> Rc<RegexContainer> {
+	/// Uses `time_zone` unless the show has its own
+	pub fn with_default_time_zone(mut self, time_zone: Option<ArchiveTimeZone>) -> Self {
+		self.time_zone = self.time_zone.or(time_zone);
+		self
+	}
+
+	pub fn regex_container(&self) -> Arc<RegexContainer> {
 		self.regex_container.get(|| RegexContainer::from(self))
 	}
 
-	pub fn date_extractor(&self) -> Option<Rc<DateExtractor<'static>>> {
-		self.date_extraction
-			.as_ref()
-			.map(DateExtraction::date_extractor)
+	pub fn request_headers(&self, secrets: &Secrets) -> Result<RequestHeaders, SecretError> {
+		let headers = super::request_headers(self.auth.as_ref(), &self.headers, secrets)?;
+
+		let mut hosts = self.auth_hosts.clone();
+		if let Some(feed_host) = url::Url::parse(&self.url)
+			.ok()
+			.and_then(|url| url.host_str().map(String::from))
+		{
+			hosts.push(feed_host);
+		}
+
+		Ok(RequestHeaders::new(headers, &hosts))
 	}
 }
 
@@ -1,5 +1,6 @@
 use super::Show;
-use crate::feed::{Clusions, DateFormat, TitleHandling};
+use crate::feed::{Clusions, DateFormat, FilenameTemplate, TitleHandling};
+use crate::secrets::Secrets;
 use chrono::NaiveDate;
 use std::error::Error;
 
@@ -41,7 +42,9 @@ fn test_parse_with_date_extraction() -> Result<(), Box<dyn Error>> {
 	assert!(show.title_strip_patterns().is_none());
 	assert!(show.regex_container().has_only_default_title_strip());
 	assert_eq!(
-		show.date_extraction().as_ref().map(|de| *de.format()),
+		show.date_extraction()
+			.as_ref()
+			.map(|de| de.format().clone()),
 		Some(DateFormat::AmericanConventional)
 	);
 	assert!(show.not_before_date().is_none());
@@ -189,3 +192,167 @@ fn test_parse_not_before_date() -> Result<(), Box<dyn Error>> {
 
 	Ok(())
 }
+
+#[test]
+fn test_parse_filename_template() -> Result<(), Box<dyn Error>> {
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"filenameTemplate": "{show} {episode:3}[ - {title}].{ext}"
+		}
+		"#;
+
+	let show: Show = serde_json::from_str(json)?;
+	assert_eq!(
+		show.filename_template(),
+		&FilenameTemplate::parse("{show} {episode:3}[ - {title}].{ext}")?
+	);
+
+	Ok(())
+}
+
+#[test]
+fn test_parse_bad_filename_template() -> Result<(), Box<dyn Error>> {
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"filenameTemplate": "{show} {nonsense}.{ext}"
+		}
+		"#;
+
+	assert!(matches!(
+		serde_json::from_str(json),
+		Result::<Show, _>::Err(_)
+	));
+
+	Ok(())
+}
+
+#[test]
+fn test_parse_auth_and_headers() -> Result<(), Box<dyn Error>> {
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"auth": {
+				"basic": {
+					"username": "listener",
+					"password": { "secret": "hardpod" }
+				}
+			},
+			"headers": {
+				"X-Api-Key": "abc123",
+				"Authorization": "overridden by auth"
+			}
+		}
+		"#;
+
+	let show: Show = serde_json::from_str(json)?;
+	let secrets: Secrets = serde_json::from_str(r#"{ "hardpod": "hunter2" }"#)?;
+
+	let headers = show.request_headers(&secrets)?;
+	assert_eq!(
+		headers.for_url("https://EXAMPLE.com/hardpod.xml"),
+		[
+			("X-Api-Key".into(), "abc123".into()),
+			(
+				"Authorization".into(),
+				"Basic bGlzdGVuZXI6aHVudGVyMg==".into()
+			),
+		]
+	);
+	// Episodes on some other CDN don't get the credentials
+	assert!(headers
+		.for_url("https://cdn.example.net/hardpod/1.mp3")
+		.is_empty());
+	assert!(show.request_headers(&Secrets::default()).is_err());
+
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"auth": { "bearer": "token" },
+			"authHosts": ["cdn.example.net"]
+		}
+		"#;
+
+	let show: Show = serde_json::from_str(json)?;
+	assert_eq!(
+		show.request_headers(&secrets)?
+			.for_url("https://cdn.example.net/hardpod/1.mp3"),
+		[("Authorization".into(), "Bearer token".into())]
+	);
+
+	Ok(())
+}
+
+#[test]
+fn test_parse_pub_date_format() -> Result<(), Box<dyn Error>> {
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"pubDateFormat": "%d.%m.%Y %H:%M"
+		}
+		"#;
+
+	let show: Show = serde_json::from_str(json)?;
+	assert_eq!(show.pub_date_format().as_deref(), Some("%d.%m.%Y %H:%M"));
+
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"pubDateFormat": "%d.%m.%Y %Q"
+		}
+		"#;
+	assert!(serde_json::from_str::<Show>(json).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn test_parse_date_pattern() -> Result<(), Box<dyn Error>> {
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"dateExtraction": {
+				"format": { "Pattern": "(?P<month>\\d{2})(?P<day>\\d{2})(?P<year>\\d{2})" },
+				"twoDigitYearPivot": 70
+			}
+		}
+		"#;
+
+	let show: Show = serde_json::from_str(json)?;
+	let (date, _, _) = show
+		.date_extraction()
+		.as_ref()
+		.and_then(|de| de.extract_date(|_| Some("Hard Pod 070497")))
+		.unwrap();
+	assert_eq!(date, NaiveDate::from_ymd_opt(1997, 7, 4).unwrap());
+
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"dateExtraction": {
+				"format": { "Pattern": "(?P<month>\\d{2})(?P<day>\\d{2})" }
+			}
+		}
+		"#;
+	assert!(serde_json::from_str::<Show>(json).is_err());
+
+	let json = r#"
+		{
+			"title": "Hard Pod",
+			"url": "https://example.com/hardpod.xml",
+			"dateExtraction": { "format": "European", "twoDigitYearPivot": 150 }
+		}
+		"#;
+	assert!(serde_json::from_str::<Show>(json).is_err());
+
+	Ok(())
+}
@@ -0,0 +1,116 @@
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use regex::Regex;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+lazy_static! {
+	static ref UTC_OFFSET_REGEX: Regex =
+		Regex::new(r#"^(?i:utc|gmt)?([+\-])(\d{1,2})(?::?(\d{2}))?$"#).unwrap();
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error(
+	"'{0}' isn't an IANA time zone name (like America/Los_Angeles) or a UTC offset (like -08:00)"
+)]
+pub struct TimeZoneError(String);
+
+/// The zone whose calendar decides which day an episode was published on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ArchiveTimeZone {
+	Named(Tz),
+	Fixed(FixedOffset),
+}
+
+impl TryFrom<String> for ArchiveTimeZone {
+	type Error = TimeZoneError;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		Self::parse(&value)
+	}
+}
+
+impl ArchiveTimeZone {
+	pub fn parse(value: &str) -> Result<Self, TimeZoneError> {
+		let value = value.trim();
+		if let Ok(tz) = value.parse::<Tz>() {
+			return Ok(Self::Named(tz));
+		}
+
+		let offset = UTC_OFFSET_REGEX.captures(value).and_then(|captures| {
+			let hours: i32 = captures[2].parse().ok()?;
+			let minutes: i32 = captures
+				.get(3)
+				.map_or(Some(0), |m| m.as_str().parse().ok())?;
+			let seconds = (hours * 60 + minutes) * 60;
+			let sign = if &captures[1] == "-" { -1 } else { 1 };
+
+			(minutes < 60)
+				.then(|| FixedOffset::east_opt(sign * seconds))
+				.flatten()
+		});
+
+		offset
+			.map(Self::Fixed)
+			.ok_or_else(|| TimeZoneError(value.into()))
+	}
+
+	/// The calendar date `date_time` falls on in this zone
+	pub fn date_of(self, date_time: &DateTime<FixedOffset>) -> NaiveDate {
+		match self {
+			Self::Named(tz) => date_time.with_timezone(&tz).date_naive(),
+			Self::Fixed(offset) => date_time.with_timezone(&offset).date_naive(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse() {
+		assert_eq!(
+			ArchiveTimeZone::parse("America/Los_Angeles"),
+			Ok(ArchiveTimeZone::Named(Tz::America__Los_Angeles))
+		);
+		assert_eq!(
+			ArchiveTimeZone::parse("UTC"),
+			Ok(ArchiveTimeZone::Named(Tz::UTC))
+		);
+		assert_eq!(
+			ArchiveTimeZone::parse("-08:00"),
+			Ok(ArchiveTimeZone::Fixed(
+				FixedOffset::west_opt(8 * 3600).unwrap()
+			))
+		);
+		assert_eq!(
+			ArchiveTimeZone::parse("UTC+0530"),
+			Ok(ArchiveTimeZone::Fixed(
+				FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap()
+			))
+		);
+		assert!(ArchiveTimeZone::parse("Mars/Olympus_Mons").is_err());
+		assert!(ArchiveTimeZone::parse("+05:75").is_err());
+		assert!(ArchiveTimeZone::parse("+25:00").is_err());
+	}
+
+	#[test]
+	fn test_date_of() {
+		// The same moment, published by two different hosts
+		let pacific = DateTime::parse_from_rfc2822("Mon, 01 Mar 2021 23:30:00 -0800").unwrap();
+		let utc = DateTime::parse_from_rfc2822("Tue, 02 Mar 2021 07:30:00 +0000").unwrap();
+
+		let los_angeles = ArchiveTimeZone::parse("America/Los_Angeles").unwrap();
+		let march_1 = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+		assert_eq!(los_angeles.date_of(&pacific), march_1);
+		assert_eq!(los_angeles.date_of(&utc), march_1);
+
+		let london = ArchiveTimeZone::parse("Europe/London").unwrap();
+		let march_2 = NaiveDate::from_ymd_opt(2021, 3, 2).unwrap();
+		assert_eq!(london.date_of(&pacific), march_2);
+		assert_eq!(london.date_of(&utc), march_2);
+	}
+}
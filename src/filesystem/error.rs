@@ -7,6 +7,9 @@ pub enum FilesystemError {
 		source: std::io::Error,
 		path: String,
 	},
+
+	#[error("{path} is in use by another arcast run")]
+	Locked { path: String },
 }
 
 impl FilesystemError {
@@ -1,15 +1,68 @@
 use super::FilesystemError;
 use std::collections::hash_set::HashSet;
-use std::fs::read_dir;
+use std::fs::{read_dir, File};
+use std::io::{BufWriter, ErrorKind, Write};
 use std::path::Path;
 
 pub fn list_files<P: AsRef<Path>>(path: P) -> Result<HashSet<String>, FilesystemError> {
 	let path_str = path.as_ref().to_string_lossy();
 	FilesystemError::handling_io_error_in(path_str, || {
-		Ok(read_dir(&path)?
+		let entries = match read_dir(&path) {
+			Ok(entries) => entries,
+			// A show that's never been archived has nothing in it yet
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashSet::new()),
+			Err(e) => return Err(e),
+		};
+
+		Ok(entries
 			.flatten()
-			.filter(|file| file.file_type().map_or(false, |ft| ft.is_file()))
+			.filter(|file| file.file_type().is_ok_and(|ft| ft.is_file()))
 			.map(|file| file.file_name().to_string_lossy().into_owned())
 			.collect())
 	})
 }
+
+pub fn create_directory<P: AsRef<Path>>(path: P) -> Result<(), FilesystemError> {
+	let path_str = path.as_ref().to_string_lossy();
+	FilesystemError::handling_io_error_in(path_str, || std::fs::create_dir_all(&path))
+}
+
+/// Writes to a temporary file in the same directory and renames it over `path` once it's complete
+/// so a crash can never leave a half-written file behind
+pub fn write_atomically<P, F>(path: P, contents: F) -> Result<(), FilesystemError>
+where
+	P: AsRef<Path>,
+	F: FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+{
+	let path = path.as_ref();
+	let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+	temporary_name.push(".tmp");
+	let temporary_path = path.with_file_name(temporary_name);
+
+	FilesystemError::handling_io_error_in(temporary_path.to_string_lossy(), || {
+		let mut writer = BufWriter::new(File::create(&temporary_path)?);
+		contents(&mut writer)?;
+		writer.flush()?;
+		writer.get_ref().sync_all()
+	})?;
+
+	rename_durably(&temporary_path, path)
+}
+
+/// Renames `from` to `to` and syncs the directory, so that the rename itself survives a crash.
+/// `from` should already be synced, so `to` is never anything but complete.
+pub fn rename_durably<P: AsRef<Path>, Q: AsRef<Path>>(
+	from: P,
+	to: Q,
+) -> Result<(), FilesystemError> {
+	let to = to.as_ref();
+	let directory = match to.parent() {
+		Some(parent) if !parent.as_os_str().is_empty() => parent,
+		_ => Path::new("."),
+	};
+
+	FilesystemError::handling_io_error_in(to.to_string_lossy(), || {
+		std::fs::rename(from, to)?;
+		File::open(directory)?.sync_all()
+	})
+}
@@ -0,0 +1,71 @@
+use super::FilesystemError;
+use std::fs::{File, TryLockError};
+use std::path::Path;
+
+const LOCK_FILENAME: &str = ".arcast.lock";
+
+/// Held for the whole run so two arcasts can't download into the same place at once.
+/// The lock is released when this is dropped (or the process dies).
+#[derive(Debug)]
+pub struct DestinationLock {
+	_file: File,
+}
+
+impl DestinationLock {
+	/// Locks `destination`, either waiting for whoever has it or failing with `FilesystemError::Locked`
+	pub fn acquire(destination: &Path, wait: bool) -> Result<Self, FilesystemError> {
+		let path = destination.join(LOCK_FILENAME);
+		let path_string = path.to_string_lossy();
+		let file = FilesystemError::handling_io_error_in(path_string.clone(), || {
+			File::options()
+				.create(true)
+				.truncate(false)
+				.write(true)
+				.open(&path)
+		})?;
+
+		match file.try_lock() {
+			Ok(()) => return Ok(Self { _file: file }),
+			Err(TryLockError::Error(e)) => {
+				return Err(FilesystemError::from_io_error(e, path_string))
+			}
+			Err(TryLockError::WouldBlock) if !wait => {
+				return Err(FilesystemError::Locked {
+					path: destination.to_string_lossy().into_owned(),
+				})
+			}
+			Err(TryLockError::WouldBlock) => (),
+		}
+
+		eprintln!(
+			"Waiting for another arcast run to finish with {}",
+			destination.display()
+		);
+		FilesystemError::handling_io_error_in(path_string, || file.lock())?;
+
+		Ok(Self { _file: file })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_second_lock_fails() -> Result<(), Box<dyn std::error::Error>> {
+		let destination = std::env::temp_dir().join(format!("arcast-lock-{}", std::process::id()));
+		std::fs::create_dir_all(&destination)?;
+
+		let lock = DestinationLock::acquire(&destination, false)?;
+		assert!(matches!(
+			DestinationLock::acquire(&destination, false),
+			Err(FilesystemError::Locked { .. })
+		));
+
+		drop(lock);
+		DestinationLock::acquire(&destination, false)?;
+
+		std::fs::remove_dir_all(&destination)?;
+		Ok(())
+	}
+}
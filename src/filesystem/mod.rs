@@ -1,5 +1,8 @@
 mod helpers;
 pub use helpers::*;
 
+mod lock;
+pub use lock::*;
+
 mod error;
 pub use error::*;
@@ -1,8 +1,9 @@
-use crate::config::Config;
 use crate::feed::{Clusions, Episode, Show};
 use crate::filesystem;
+use crate::manifest::Manifest;
 use regex::Regex;
 use std::collections::HashSet;
+use std::path::Path;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum EpisodeStatus {
@@ -37,6 +38,7 @@ fn classified_episodes_from_set<'a>(
 	show: &Show,
 	all_episodes: &'a [Episode],
 	existing_files: HashSet<String>,
+	known_guids: HashSet<String>,
 ) -> impl Iterator<Item = ClassifiedEpisode<'a>> {
 	let clusions = show.regex_container().clusions().clone();
 
@@ -62,7 +64,11 @@ fn classified_episodes_from_set<'a>(
 				}
 			}
 
-			let already_have = existing_files.contains(episode.filename());
+			// GUIDs survive retitling, so only fall back to the filename when we haven't seen it
+			let already_have = match episode.guid() {
+				Some(guid) if known_guids.contains(guid) => true,
+				_ => existing_files.contains(episode.filename()),
+			};
 			if already_have {
 				Have
 			} else {
@@ -85,10 +91,13 @@ fn classified_episodes_from_set<'a>(
 pub fn classified_episodes<'a>(
 	show: &Show,
 	all_episodes: &'a [Episode],
-	config: &Config,
+	destination: &Path,
+	manifest: &Manifest,
 ) -> Result<impl Iterator<Item = ClassifiedEpisode<'a>>, filesystem::FilesystemError> {
-	let existing_files = filesystem::list_files(config.destination())?;
-	let filtered_eps = classified_episodes_from_set(show, all_episodes, existing_files);
+	let existing_files = filesystem::list_files(destination)?;
+	let known_guids = manifest.known_guids().clone();
+	let filtered_eps =
+		classified_episodes_from_set(show, all_episodes, existing_files, known_guids);
 
 	Ok(filtered_eps)
 }
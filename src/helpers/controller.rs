@@ -1,37 +1,351 @@
 use crate::config::Config;
-use crate::helpers;
+use crate::download::{self, DownloadContext, FeedCache};
+use crate::feed;
+use crate::feed::{Episode, RequestHeaders};
+use crate::filesystem::{self, DestinationLock, FilesystemError};
+use crate::helpers::{self, DownloadClientError, ProgressBoard, ShowSummary};
+use crate::interrupt;
+use crate::library::LibraryEntry;
+use crate::manifest::{Manifest, ManifestError};
+use crate::secrets::{SecretError, Secrets};
+use std::collections::HashSet;
 use std::error::Error;
+use std::path::Path;
+use std::sync::mpsc;
+
+pub fn sync_show(
+	entry: &LibraryEntry,
+	context: &DownloadContext,
+	secrets: &Secrets,
+	config: &Config,
+) -> Result<ShowSummary, Box<dyn Error>> {
+	let show = entry.show();
+	let destination = entry.destination();
+	let headers = request_headers(entry, secrets, config)?;
+
+	if config.may_download() {
+		filesystem::create_directory(destination)?;
+	}
+	let _lock = lock_destination(destination, config)?;
+
+	let episodes = fetch_episodes(entry, context, &headers, config)?;
+	let mut manifest = Manifest::load(destination)?;
+	let classified_eps = helpers::classified_episodes(show, &episodes, destination, &manifest)?;
+
+	process_classified_episodes(
+		classified_eps,
+		destination,
+		context,
+		&headers,
+		&mut manifest,
+		config,
+	)
+}
+
+/// Shows archived outside the root destination need their own lock, since the run's lock on the
+/// root doesn't keep another run (with a different root) out of them
+pub(super) fn lock_destination(
+	destination: &Path,
+	config: &Config,
+) -> Result<Option<DestinationLock>, FilesystemError> {
+	(destination != config.destination() && destination.is_dir())
+		.then(|| DestinationLock::acquire(destination, config.wait_for_lock()))
+		.transpose()
+}
+
+/// The show's headers, which (with their secrets) are only needed when going online
+pub(super) fn request_headers(
+	entry: &LibraryEntry,
+	secrets: &Secrets,
+	config: &Config,
+) -> Result<RequestHeaders, SecretError> {
+	if config.offline() {
+		return Ok(RequestHeaders::default());
+	}
+	entry.show().request_headers(secrets)
+}
+
+/// The show's episodes, from the network or (with `--offline`) the feed cache
+pub(super) fn fetch_episodes(
+	entry: &LibraryEntry,
+	context: &DownloadContext,
+	headers: &RequestHeaders,
+	config: &Config,
+) -> Result<Vec<Episode>, Box<dyn Error>> {
+	let show = entry.show();
+	let destination = entry.destination();
+
+	// Pretending shouldn't create directories, but it can still use (and refresh) an existing cache
+	let feed_cache = destination.is_dir().then(|| FeedCache::new(destination));
+	let parsed = if config.offline() {
+		let reader = download::cached_feed(show.url(), feed_cache.as_ref())?;
+		feed::episodes_from_reader(reader, show)?
+	} else {
+		let reader = download::download_feed(context, show.url(), feed_cache.as_ref(), headers)?;
+		feed::episodes_from_reader(reader, show)?
+	};
+
+	for rejected in &parsed.rejected {
+		eprintln!("Warning: {}: skipping {}", show.title(), rejected);
+	}
+	if config.strict() && !parsed.rejected.is_empty() {
+		return Err(feed::ParsingError::RejectedItems {
+			count: parsed.rejected.len(),
+			total: parsed.item_count(),
+		}
+		.into());
+	}
+
+	Ok(parsed.episodes)
+}
+
+/// The episode, how many bytes were reserved for it, how many were actually transferred,
+/// and how its download went
+type DownloadResult<'a> = (
+	&'a Episode,
+	u64,
+	u64,
+	Result<usize, Box<DownloadClientError>>,
+);
+
+/// The main thread's view of a show's downloads, some of which may be running on other threads
+struct Downloads<'a, 'c> {
+	destination: &'c Path,
+	context: &'c DownloadContext,
+	headers: &'c RequestHeaders,
+	manifest: &'c mut Manifest,
+	config: &'c Config,
+	progress_board: Option<&'c ProgressBoard>,
+	summary: ShowSummary,
+	results: mpsc::Receiver<DownloadResult<'a>>,
+	in_flight: usize,
+	/// Two episodes can end up with the same filename (say, with `stripWholeTitle` and two on the
+	/// same day), and they mustn't both write to the same partial file at once
+	in_flight_filenames: HashSet<&'a str>,
+	stopping: bool,
+	adopted_existing: bool,
+}
+
+impl<'a, 'c> Downloads<'a, 'c> {
+	fn report(&self, message: impl FnOnce()) {
+		match self.progress_board {
+			Some(board) => board.suspend(message),
+			None => message(),
+		}
+	}
+
+	/// Whether to stop starting new downloads
+	fn should_stop(&self) -> bool {
+		self.stopping || interrupt::interrupted()
+	}
+
+	fn have(&mut self, episode: &Episode) {
+		if self.config.print_existing_episodes() {
+			self.report(|| println!("{} already exists", episode.filename()));
+		}
+
+		// Files from before the manifest existed get recorded so they survive retitling
+		if self.config.may_download() && !self.manifest.contains(episode) {
+			let path = self.destination.join(episode.filename());
+			if let Ok(metadata) = std::fs::metadata(path) {
+				self.manifest.adopt(episode, metadata.len());
+				self.adopted_existing = true;
+			}
+		}
+		self.summary.existing += 1;
+	}
+
+	/// Sets aside room in the run's byte budget for `episode`, returning how much,
+	/// or `None` if it would go over
+	fn reserve_budget(&self, episode: &Episode) -> Option<u64> {
+		let budget = self.context.budget();
+		if !budget.is_limited() {
+			return Some(0);
+		}
+
+		// Only bother the server about the size when we're about to download it anyway
+		let estimate = episode
+			.enclosure_length()
+			.or_else(|| {
+				self.config
+					.may_download()
+					.then(|| {
+						download::remote_size(self.context, episode.enclosure_url(), self.headers)
+					})
+					.flatten()
+			})
+			.unwrap_or(0);
+
+		if budget.reserve(estimate) {
+			Some(estimate)
+		} else {
+			self.report(|| {
+				println!(
+					"Not downloading {} or anything after it: it would go over --max-bytes",
+					episode.filename()
+				);
+			});
+			None
+		}
+	}
+
+	fn wait_for_one(&mut self) -> Result<(), ManifestError> {
+		let Ok((episode, estimate, transferred, result)) = self.results.recv() else {
+			self.in_flight = 0;
+			self.in_flight_filenames.clear();
+			return Ok(());
+		};
+		self.in_flight -= 1;
+		self.in_flight_filenames.remove(episode.filename().as_str());
+
+		// A resumed download only costs what it fetched this time, and a failed one still costs what it got
+		self.context.budget().settle(estimate, transferred);
+
+		match result {
+			Ok(size) => {
+				// Feeds are often a little off (or way off, with dynamically inserted ads), so this is only a warning
+				if let Some(expected) = episode.enclosure_length().filter(|&len| len != size as u64)
+				{
+					self.report(|| {
+						eprintln!(
+							"Warning: {}: downloaded {} bytes, but the feed says {}",
+							episode.filename(),
+							size,
+							expected
+						);
+					});
+				}
+
+				self.manifest.record(episode, size as u64);
+				self.manifest.save(self.destination)?;
+				self.summary.downloaded += 1;
+			}
+			Err(e) if e.was_interrupted() => {
+				self.report(|| {
+					if e.download_path().exists() {
+						eprintln!("Keeping partial download {}", e.download_path().display());
+					}
+				});
+			}
+			Err(e) => {
+				self.report(|| {
+					// The partial file is left in place so the next run can resume it
+					if e.download_path().exists() {
+						eprintln!("Keeping partial download {}", e.download_path().display());
+					}
+					eprintln!("Error: {}: {}", episode.filename(), e);
+				});
+				self.summary.record_failure(episode.filename(), &*e);
+
+				if !self.config.keep_going() {
+					self.stopping = true;
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
 
 pub fn process_classified_episodes<'a>(
 	episodes: impl Iterator<Item = helpers::ClassifiedEpisode<'a>>,
+	destination: &Path,
+	context: &DownloadContext,
+	headers: &RequestHeaders,
+	manifest: &mut Manifest,
 	config: &Config,
-) -> Result<usize, Box<dyn Error>> {
-	let mut missing_processed = 0;
-	for classified_episode in episodes {
-		let (status, episode) = classified_episode.take();
-		if missing_processed >= config.number_to_download() {
-			break;
-		}
-
-		match status {
-			helpers::EpisodeStatus::Have => {
-				if config.print_existing_episodes() {
-					println!("{} already exists", episode.filename())
-				}
+) -> Result<ShowSummary, Box<dyn Error>> {
+	let limit = config.number_to_download();
+	let progress_board = if config.may_download() {
+		ProgressBoard::new()
+	} else {
+		None
+	};
+
+	std::thread::scope(|scope| {
+		let (sender, results) = mpsc::channel();
+		let mut downloads = Downloads {
+			destination,
+			context,
+			headers,
+			manifest,
+			config,
+			progress_board: progress_board.as_ref(),
+			summary: ShowSummary::default(),
+			results,
+			in_flight: 0,
+			in_flight_filenames: HashSet::new(),
+			stopping: false,
+			adopted_existing: false,
+		};
+
+		for classified_episode in episodes {
+			let (status, episode) = classified_episode.take();
+
+			// Anything still in flight might fail and leave room under the limit
+			while downloads.in_flight > 0
+				&& downloads.summary.downloaded + downloads.in_flight >= limit
+			{
+				downloads.wait_for_one()?;
+			}
+			if downloads.should_stop() || downloads.summary.downloaded >= limit {
+				break;
 			}
-			helpers::EpisodeStatus::Need => {
-				if config.pretend() {
+
+			match status {
+				helpers::EpisodeStatus::Have => downloads.have(episode),
+				helpers::EpisodeStatus::Need if !config.may_download() => {
+					if downloads.reserve_budget(episode).is_none() {
+						break;
+					}
 					println!("{} would be downloaded", episode.filename());
-				} else if let Err(e) = helpers::download_episode(episode, config) {
-					// If there was an error, try to remove the partial file
-					let _ = std::fs::remove_file(e.download_path());
-					return Err(e);
+					downloads.summary.downloaded += 1;
+				}
+				helpers::EpisodeStatus::Need => {
+					// Just like with one job, the later episode replaces the earlier one
+					while downloads.in_flight >= config.jobs()
+						|| downloads
+							.in_flight_filenames
+							.contains(episode.filename().as_str())
+					{
+						downloads.wait_for_one()?;
+					}
+					if downloads.should_stop() {
+						break;
+					}
+					let Some(estimate) = downloads.reserve_budget(episode) else {
+						break;
+					};
+
+					let sender = sender.clone();
+					let progress_board = downloads.progress_board;
+					scope.spawn(move || {
+						let mut transferred = 0;
+						let result = helpers::download_episode(
+							episode,
+							destination,
+							context,
+							headers,
+							&mut transferred,
+							progress_board,
+						);
+						let _ = sender.send((episode, estimate, transferred, result));
+					});
+					downloads.in_flight += 1;
+					downloads.in_flight_filenames.insert(episode.filename());
 				}
-				missing_processed += 1;
+				helpers::EpisodeStatus::ShouldSkip => downloads.summary.skipped += 1,
 			}
-			helpers::EpisodeStatus::ShouldSkip => (),
 		}
-	}
 
-	Ok(missing_processed)
+		while downloads.in_flight > 0 {
+			downloads.wait_for_one()?;
+		}
+
+		if downloads.adopted_existing {
+			downloads.manifest.save(destination)?;
+		}
+
+		Ok(downloads.summary)
+	})
 }
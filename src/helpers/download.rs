@@ -1,12 +1,11 @@
-use super::progress_bars::TitledBar;
-use crate::config::Config;
-use crate::download::{download_to_file, DownloadError};
-use crate::feed::Episode;
+use super::progress_bars::ProgressBoard;
+use crate::download::{download_to_partial_file, partial_path, DownloadContext, DownloadError};
+use crate::feed::{Episode, RequestHeaders};
+use crate::filesystem;
 use getset::Getters;
 use std::boxed::Box;
 use std::fmt::Display;
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error as TError;
 
 #[derive(TError, Debug, Getters)]
@@ -23,6 +22,10 @@ impl DownloadClientError {
 			source,
 		}
 	}
+
+	pub fn was_interrupted(&self) -> bool {
+		matches!(self.source, DownloadError::Interrupted)
+	}
 }
 
 impl Display for DownloadClientError {
@@ -31,38 +34,46 @@ impl Display for DownloadClientError {
 	}
 }
 
+/// Downloads to a `.part` file and only renames it to the episode's filename once it's complete
+/// and on disk, so a file with that name is always a whole episode
 pub fn download_episode(
 	episode: &Episode,
-	config: &Config,
-) -> Result<(), Box<DownloadClientError>> {
-	let mut file_dest_path = config.destination().to_path_buf();
+	destination: &Path,
+	context: &DownloadContext,
+	headers: &RequestHeaders,
+	transferred: &mut u64,
+	progress_board: Option<&ProgressBoard>,
+) -> Result<usize, Box<DownloadClientError>> {
+	let mut file_dest_path = destination.to_path_buf();
 	file_dest_path.push(episode.filename());
+	let part_path = partial_path(&file_dest_path);
 
-	#[allow(clippy::option_if_let_else)]
-	let progress_function: Box<dyn FnMut(f64)> =
-		if let Some(terminal_size) = terminal_size::terminal_size() {
-			let mut stdout = termion::cursor::HideCursor::from(std::io::stdout());
-
-			let terminal_width = terminal_size.0 .0;
-			let label = episode.filename();
-
-			let mut bar = TitledBar::new(label, terminal_width);
+	let bar_id = progress_board.map(|board| board.start(episode.filename()));
+	let progress_function = |prog: f64| {
+		if let (Some(board), Some(id)) = (progress_board, bar_id) {
+			board.set(id, prog);
+		}
+	};
 
-			Box::new(move |prog: f64| {
-				bar.set(prog);
-				let _ = write!(stdout, "\r{}", bar);
-				let _ = stdout.flush();
-			})
-		} else {
-			Box::new(|_| {})
-		};
+	let result = download_to_partial_file(
+		episode.enclosure_url(),
+		&part_path,
+		context,
+		headers,
+		transferred,
+		progress_function,
+	)
+	.and_then(|size| {
+		filesystem::rename_durably(&part_path, &file_dest_path)?;
+		Ok(size)
+	});
 
-	if let Err(e) = download_to_file(episode.enclosure_url(), &file_dest_path, progress_function) {
-		let new_error = DownloadClientError::new(*e, file_dest_path);
-		return Err(Box::new(new_error));
+	if let (Some(board), Some(id)) = (progress_board, bar_id) {
+		board.finish(id);
 	}
 
-	println!();
-
-	Ok(())
+	result.map_err(|e| {
+		let new_error = DownloadClientError::new(*e, part_path);
+		Box::new(new_error)
+	})
 }
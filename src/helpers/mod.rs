@@ -8,3 +8,10 @@ mod download;
 pub use download::*;
 
 mod progress_bars;
+pub use progress_bars::ProgressBoard;
+
+mod summary;
+pub use summary::*;
+
+mod verify;
+pub use verify::*;
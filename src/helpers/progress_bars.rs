@@ -73,3 +73,136 @@ impl<'a> std::fmt::Display for TitledBar<'a> {
 		write!(f, "{} {} {}", sub_title, self.bar, percent)
 	}
 }
+
+struct BoardEntry {
+	id: usize,
+	title: String,
+	progress: f64,
+}
+
+struct BoardState {
+	stdout: termion::cursor::HideCursor<std::io::Stdout>,
+	entries: Vec<BoardEntry>,
+	next_id: usize,
+	drawn_lines: u16,
+}
+
+/// A stack of `TitledBar`s, one per active transfer, redrawn in place at the bottom of the terminal
+pub struct ProgressBoard {
+	width: u16,
+	state: std::sync::Mutex<BoardState>,
+}
+
+impl ProgressBoard {
+	/// Returns `None` if we're not attached to a terminal
+	pub fn new() -> Option<Self> {
+		let (terminal_width, _) = terminal_size::terminal_size()?;
+
+		Some(Self {
+			width: terminal_width.0,
+			state: std::sync::Mutex::new(BoardState {
+				stdout: termion::cursor::HideCursor::from(std::io::stdout()),
+				entries: Vec::new(),
+				next_id: 0,
+				drawn_lines: 0,
+			}),
+		})
+	}
+
+	pub fn start(&self, title: &str) -> usize {
+		let mut state = self.lock();
+		let id = state.next_id;
+		state.next_id += 1;
+		state.entries.push(BoardEntry {
+			id,
+			title: title.into(),
+			progress: 0.0,
+		});
+		self.redraw(&mut state, None);
+
+		id
+	}
+
+	pub fn set(&self, id: usize, progress: f64) {
+		let mut state = self.lock();
+		let Some(entry) = state.entries.iter_mut().find(|e| e.id == id) else {
+			return;
+		};
+
+		// Redrawing every bar for every chunk would be a lot of terminal traffic
+		let changed = (entry.progress * 100.0) as i32 != (progress * 100.0) as i32;
+		entry.progress = progress;
+
+		if changed {
+			self.redraw(&mut state, None);
+		}
+	}
+
+	/// Removes the bar from the board, leaving its final state in the scrollback
+	pub fn finish(&self, id: usize) {
+		let mut state = self.lock();
+		let Some(index) = state.entries.iter().position(|e| e.id == id) else {
+			return;
+		};
+
+		let entry = state.entries.remove(index);
+		let mut bar = TitledBar::new(&entry.title, self.width);
+		bar.set(entry.progress);
+		self.redraw(&mut state, Some(&bar.to_string()));
+	}
+
+	/// Clears the board while `f` prints other output, then redraws it underneath
+	pub fn suspend<F: FnOnce()>(&self, f: F) {
+		use std::io::Write;
+
+		let mut state = self.lock();
+		let drawn_lines = state.drawn_lines;
+		if drawn_lines > 0 {
+			let _ = write!(
+				state.stdout,
+				"{}\r{}",
+				termion::cursor::Up(drawn_lines),
+				termion::clear::AfterCursor
+			);
+			let _ = state.stdout.flush();
+			state.drawn_lines = 0;
+		}
+
+		f();
+
+		self.redraw(&mut state, None);
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, BoardState> {
+		self.state
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	fn redraw(&self, state: &mut BoardState, scrollback_line: Option<&str>) {
+		use std::io::Write;
+
+		let mut output = String::new();
+		if state.drawn_lines > 0 {
+			output.push_str(&termion::cursor::Up(state.drawn_lines).to_string());
+		}
+		output.push('\r');
+		output.push_str(termion::clear::AfterCursor.as_ref());
+
+		if let Some(line) = scrollback_line {
+			output.push_str(line);
+			output.push('\n');
+		}
+
+		for entry in &state.entries {
+			let mut bar = TitledBar::new(&entry.title, self.width);
+			bar.set(entry.progress);
+			output.push_str(&bar.to_string());
+			output.push('\n');
+		}
+
+		state.drawn_lines = state.entries.len() as u16;
+		let _ = write!(state.stdout, "{}", output);
+		let _ = state.stdout.flush();
+	}
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeFailure {
+	pub filename: String,
+	pub reason: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShowSummary {
+	pub downloaded: usize,
+	pub existing: usize,
+	pub skipped: usize,
+	pub failed: Vec<EpisodeFailure>,
+}
+
+impl ShowSummary {
+	pub fn record_failure(&mut self, filename: impl Into<String>, reason: &dyn Error) {
+		self.failed.push(EpisodeFailure {
+			filename: filename.into(),
+			reason: reason.to_string(),
+		});
+	}
+}
+
+#[derive(Debug)]
+struct ShowOutcome {
+	title: String,
+	result: Result<ShowSummary, String>,
+}
+
+#[derive(Debug)]
+pub struct RunSummary {
+	pretend: bool,
+	outcomes: Vec<ShowOutcome>,
+}
+
+impl RunSummary {
+	pub fn new(pretend: bool) -> Self {
+		Self {
+			pretend,
+			outcomes: Vec::new(),
+		}
+	}
+
+	pub fn record(
+		&mut self,
+		title: impl Into<String>,
+		result: Result<ShowSummary, Box<dyn Error>>,
+	) {
+		self.outcomes.push(ShowOutcome {
+			title: title.into(),
+			result: result.map_err(|e| e.to_string()),
+		});
+	}
+
+	pub fn failed_show_count(&self) -> usize {
+		self.outcomes.iter().filter(|o| o.result.is_err()).count()
+	}
+
+	pub fn failed_episode_count(&self) -> usize {
+		self.outcomes
+			.iter()
+			.filter_map(|o| o.result.as_ref().ok())
+			.map(|summary| summary.failed.len())
+			.sum()
+	}
+
+	pub fn has_failures(&self) -> bool {
+		self.failed_show_count() > 0 || self.failed_episode_count() > 0
+	}
+}
+
+impl Display for RunSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let downloaded_verb = if self.pretend {
+			"would be downloaded"
+		} else {
+			"downloaded"
+		};
+
+		writeln!(f, "Summary:")?;
+		for outcome in &self.outcomes {
+			match &outcome.result {
+				Ok(summary) => {
+					writeln!(
+						f,
+						"  {}: {} {}, {} already present, {} skipped, {} failed",
+						outcome.title,
+						summary.downloaded,
+						downloaded_verb,
+						summary.existing,
+						summary.skipped,
+						summary.failed.len()
+					)?;
+
+					for failure in &summary.failed {
+						writeln!(f, "    {}: {}", failure.filename, failure.reason)?;
+					}
+				}
+				Err(e) => writeln!(f, "  {}: failed: {}", outcome.title, e)?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_summary_display() {
+		let mut hard_pod = ShowSummary {
+			downloaded: 2,
+			existing: 10,
+			skipped: 1,
+			..Default::default()
+		};
+		hard_pod.record_failure("Hard Pod - 2021-02-21.mp3", &*Box::<dyn Error>::from("404"));
+
+		let mut summary = RunSummary::new(false);
+		summary.record("Hard Pod", Ok(hard_pod));
+		summary.record("Soft Pod", Err("feed went away".into()));
+
+		assert_eq!(summary.failed_show_count(), 1);
+		assert_eq!(summary.failed_episode_count(), 1);
+		assert!(summary.has_failures());
+		assert_eq!(
+			summary.to_string(),
+			"Summary:\n  Hard Pod: 2 downloaded, 10 already present, 1 skipped, 1 failed\n    Hard Pod - 2021-02-21.mp3: 404\n  Soft Pod: failed: feed went away\n"
+		);
+	}
+
+	#[test]
+	fn test_summary_without_failures() {
+		let mut summary = RunSummary::new(true);
+		summary.record("Hard Pod", Ok(ShowSummary::default()));
+
+		assert!(!summary.has_failures());
+	}
+}
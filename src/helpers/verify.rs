@@ -0,0 +1,281 @@
+use super::controller::{fetch_episodes, lock_destination, request_headers};
+use crate::config::Config;
+use crate::download::{self, partial_path, DownloadContext};
+use crate::feed::{Episode, RequestHeaders};
+use crate::filesystem::{self, FilesystemError};
+use crate::interrupt;
+use crate::library::LibraryEntry;
+use crate::manifest::Manifest;
+use crate::media::MediaKind;
+use crate::secrets::Secrets;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+
+/// What's wrong with an archived episode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+	Empty,
+	Truncated { size: u64, expected: u64 },
+	NotMedia(MediaKind),
+}
+
+impl Display for Problem {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Empty => write!(f, "empty"),
+			Self::Truncated { size, expected } => {
+				write!(f, "truncated ({} of {} bytes)", size, expected)
+			}
+			Self::NotMedia(kind) => write!(f, "not audio or video ({})", kind),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileProblem {
+	pub filename: String,
+	pub problem: Problem,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShowVerification {
+	pub checked: usize,
+	pub problems: Vec<FileProblem>,
+	/// How many of the problems were re-queued for download
+	pub requeued: usize,
+}
+
+/// Checks `size` and the first bytes of a file against how big it should be
+fn problem_with(size: u64, expected: Option<u64>, kind: MediaKind) -> Option<Problem> {
+	if size == 0 {
+		return Some(Problem::Empty);
+	}
+	// The same rule downloads go by, so we never flag something a sync would happily keep.
+	// An error page is usually short too, but resuming it would just make things worse.
+	if !kind.accepts_download(None) {
+		return Some(Problem::NotMedia(kind));
+	}
+
+	expected
+		.filter(|&expected| size < expected)
+		.map(|expected| Problem::Truncated { size, expected })
+}
+
+/// How big a file should be: what arcast downloaded is the best guide, since feeds' lengths are
+/// often a little off, but a file it only found (which might be the truncated one) has to go by
+/// the feed, or failing that the server
+fn expected_size(
+	downloaded: Option<u64>,
+	enclosure_length: Option<u64>,
+	remote_size: impl FnOnce() -> Option<u64>,
+) -> Option<u64> {
+	downloaded.or(enclosure_length).or_else(remote_size)
+}
+
+/// Episodes, and the files arcast leaves alongside them: `.part` and `.validator` files, and
+/// dotfiles like the manifest, lock, and feed cache
+fn is_episode_file(filename: &str) -> bool {
+	!filename.starts_with('.') && !filename.ends_with(".part") && !filename.ends_with(".validator")
+}
+
+/// Truncated files become partial downloads to resume, anything else is just deleted
+fn requeue(path: &Path, problem: Problem) -> Result<(), FilesystemError> {
+	let path_string = path.to_string_lossy();
+	FilesystemError::handling_io_error_in(path_string, || match problem {
+		Problem::Truncated { .. } => std::fs::rename(path, partial_path(path)),
+		Problem::Empty | Problem::NotMedia(_) => std::fs::remove_file(path),
+	})
+}
+
+fn check_file(
+	filename: &str,
+	path: &Path,
+	episode: Option<&Episode>,
+	manifest: &Manifest,
+	context: &DownloadContext,
+	headers: &RequestHeaders,
+	config: &Config,
+) -> Result<Option<Problem>, FilesystemError> {
+	let path_string = path.to_string_lossy();
+	let size =
+		FilesystemError::handling_io_error_in(path_string.clone(), || std::fs::metadata(path))?
+			.len();
+	let kind = FilesystemError::handling_io_error_in(path_string, || MediaKind::of_file(path))?;
+
+	let downloaded = manifest
+		.record_named(filename)
+		.filter(|record| !record.adopted())
+		.map(|record| *record.size());
+	let enclosure_length = episode.and_then(|episode| *episode.enclosure_length());
+	let expected = expected_size(downloaded, enclosure_length, || {
+		let url = episode?.enclosure_url();
+		(!config.offline())
+			.then(|| download::remote_size(context, url, headers))
+			.flatten()
+	});
+
+	let problem = problem_with(size, expected, kind);
+	if let Some(length) = enclosure_length
+		.filter(|&length| downloaded.is_some() && problem.is_none() && length != size)
+	{
+		eprintln!(
+			"Warning: {}: {} bytes, but the feed says {}",
+			filename, size, length
+		);
+	}
+
+	Ok(problem)
+}
+
+pub fn verify_show(
+	entry: &LibraryEntry,
+	context: &DownloadContext,
+	secrets: &Secrets,
+	config: &Config,
+) -> Result<ShowVerification, Box<dyn Error>> {
+	let destination = entry.destination();
+	let _lock = lock_destination(destination, config)?;
+	let headers = request_headers(entry, secrets, config)?;
+	let episodes = fetch_episodes(entry, context, &headers, config)?;
+	let mut manifest = Manifest::load(destination)?;
+
+	// Files are matched to episodes by the name they were archived under
+	let episodes_by_filename: HashMap<String, &Episode> = episodes
+		.iter()
+		.map(|episode| {
+			let filename = manifest.filename_of(episode).unwrap_or(episode.filename());
+			(filename.to_owned(), episode)
+		})
+		.collect();
+
+	let mut filenames: Vec<String> = filesystem::list_files(destination)?
+		.into_iter()
+		.filter(|filename| is_episode_file(filename))
+		.collect();
+	filenames.sort();
+
+	let mut verification = ShowVerification::default();
+	for filename in filenames {
+		if interrupt::interrupted() {
+			break;
+		}
+
+		let path = destination.join(&filename);
+		let episode = episodes_by_filename.get(&filename).copied();
+
+		verification.checked += 1;
+		let Some(problem) = check_file(
+			&filename, &path, episode, &manifest, context, &headers, config,
+		)?
+		else {
+			continue;
+		};
+
+		println!("{}: {}", filename, problem);
+		if config.requeue() {
+			match episode {
+				Some(episode) => {
+					requeue(&path, problem)?;
+					manifest.forget(episode);
+					verification.requeued += 1;
+				}
+				// Deleting it would lose it for good, since the next sync couldn't get it back
+				None => println!(
+					"{}: not in the feed any more, so leaving it alone",
+					filename
+				),
+			}
+		}
+		verification
+			.problems
+			.push(FileProblem { filename, problem });
+	}
+
+	if verification.requeued > 0 {
+		manifest.save(destination)?;
+	}
+
+	Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_is_episode_file() {
+		assert!(is_episode_file("Show - 2021-03-04 - Title.mp3"));
+		assert!(is_episode_file("Show - 2021-03-04 - Title.mov"));
+		assert!(!is_episode_file("Show - 2021-03-04 - Title.mp3.part"));
+		assert!(!is_episode_file(
+			"Show - 2021-03-04 - Title.mp3.part.validator"
+		));
+		assert!(!is_episode_file(".arcast-manifest.json"));
+		assert!(!is_episode_file(".arcast.lock"));
+	}
+
+	#[test]
+	fn test_expected_size() {
+		let unreachable = || -> Option<u64> { panic!("shouldn't need to ask the server") };
+		assert_eq!(expected_size(Some(90), Some(100), unreachable), Some(90));
+		assert_eq!(expected_size(None, Some(100), unreachable), Some(100));
+		assert_eq!(expected_size(None, None, || Some(80)), Some(80));
+		assert_eq!(expected_size(None, None, || None), None);
+	}
+
+	#[test]
+	fn test_problem_with() {
+		assert_eq!(
+			problem_with(0, Some(100), MediaKind::Unknown),
+			Some(Problem::Empty)
+		);
+		assert_eq!(
+			problem_with(60, Some(100), MediaKind::Mpeg),
+			Some(Problem::Truncated {
+				size: 60,
+				expected: 100
+			})
+		);
+		assert_eq!(
+			problem_with(100, None, MediaKind::Markup),
+			Some(Problem::NotMedia(MediaKind::Markup))
+		);
+		assert_eq!(
+			problem_with(20, Some(100), MediaKind::Markup),
+			Some(Problem::NotMedia(MediaKind::Markup))
+		);
+		assert_eq!(problem_with(120, Some(100), MediaKind::Mp4), None);
+		assert_eq!(problem_with(100, None, MediaKind::Mpeg), None);
+		// QuickTime, AIFF, and the like aren't recognized, but they aren't errors either
+		assert_eq!(problem_with(100, Some(100), MediaKind::Unknown), None);
+	}
+
+	#[test]
+	fn test_requeue() -> Result<(), Box<dyn Error>> {
+		let directory = std::env::temp_dir().join(format!("arcast-verify-{}", std::process::id()));
+		std::fs::create_dir_all(&directory)?;
+
+		let truncated = directory.join("Truncated.mp3");
+		let html = directory.join("Error Page.mp3");
+		std::fs::write(&truncated, b"ID3")?;
+		std::fs::write(&html, b"<html></html>")?;
+
+		requeue(
+			&truncated,
+			Problem::Truncated {
+				size: 3,
+				expected: 100,
+			},
+		)?;
+		requeue(&html, Problem::NotMedia(MediaKind::Markup))?;
+
+		assert!(!truncated.exists());
+		assert_eq!(std::fs::read(partial_path(&truncated))?, b"ID3");
+		assert!(!html.exists());
+
+		std::fs::remove_dir_all(&directory)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,53 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl-C and SIGTERM so downloads can stop cleanly, keeping their partial files to
+/// resume next time. A second one exits right away.
+pub fn install() -> std::io::Result<()> {
+	let mut signals = Signals::new([SIGINT, SIGTERM])?;
+
+	std::thread::spawn(move || {
+		for _ in signals.forever() {
+			if INTERRUPTED.swap(true, Ordering::SeqCst) {
+				// The progress board hid the cursor and won't get the chance to show it again
+				let mut stdout = std::io::stdout();
+				let _ = write!(stdout, "{}", termion::cursor::Show);
+				let _ = stdout.flush();
+
+				eprintln!("\nInterrupted again, exiting immediately");
+				std::process::exit(130);
+			}
+
+			eprintln!("\nInterrupted, stopping downloads (interrupt again to quit immediately)");
+		}
+	});
+
+	Ok(())
+}
+
+pub fn interrupted() -> bool {
+	INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Sleeps for `duration`, or until we're interrupted. Returns whether it slept the whole time.
+pub fn sleep(duration: Duration) -> bool {
+	// Signals don't wake other threads, so check every so often rather than sleeping it all at once
+	const CHECK_EVERY: Duration = Duration::from_millis(100);
+
+	let end = Instant::now() + duration;
+	loop {
+		if interrupted() {
+			return false;
+		}
+		let remaining = end.saturating_duration_since(Instant::now());
+		if remaining.is_zero() {
+			return true;
+		}
+		std::thread::sleep(remaining.min(CHECK_EVERY));
+	}
+}
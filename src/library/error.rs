@@ -0,0 +1,14 @@
+use crate::filesystem::FilesystemError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LibraryError {
+	#[error(transparent)]
+	Filesystem(#[from] FilesystemError),
+
+	#[error("{path}: {source}")]
+	Parsing {
+		source: serde_json::Error,
+		path: String,
+	},
+}
@@ -0,0 +1,192 @@
+use super::LibraryError;
+use crate::feed::{ArchiveTimeZone, Show};
+use crate::filesystem::FilesystemError;
+use getset::Getters;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The on-disk shape of a multi-show configuration file
+#[derive(Deserialize, Debug)]
+struct LibraryFile {
+	shows: Vec<Show>,
+
+	/// For every show that doesn't set its own
+	#[serde(default)]
+	timezone: Option<ArchiveTimeZone>,
+}
+
+#[derive(Debug, Getters)]
+#[get = "pub"]
+pub struct LibraryEntry {
+	show: Show,
+	destination: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Library {
+	entries: Vec<LibraryEntry>,
+}
+
+impl Library {
+	/// Loads either a single show description or a library (`{ "shows": [...] }`)
+	/// Single shows are archived directly into `root_destination` unless they set `destination`,
+	/// while library entries default to a subdirectory named after the show
+	pub fn from_path(path: &Path, root_destination: &Path) -> Result<Self, LibraryError> {
+		let path_string = path.to_string_lossy();
+		let file_handle = FilesystemError::handling_io_error_in(path_string.clone(), || {
+			std::fs::File::open(path)
+		})?;
+
+		serde_json::from_reader(file_handle)
+			.and_then(|value| Self::from_value(value, root_destination))
+			.map_err(|source| LibraryError::Parsing {
+				source,
+				path: path_string.into_owned(),
+			})
+	}
+
+	fn from_value(
+		value: serde_json::Value,
+		root_destination: &Path,
+	) -> Result<Self, serde_json::Error> {
+		let is_library = value.get("shows").is_some();
+
+		let entries = if is_library {
+			let file: LibraryFile = serde_json::from_value(value)?;
+			file.shows
+				.into_iter()
+				.map(|show| {
+					let show = show.with_default_time_zone(file.timezone);
+					let subdirectory = show
+						.destination()
+						.clone()
+						.unwrap_or_else(|| PathBuf::from(show.title().replace('/', "-")));
+
+					LibraryEntry {
+						destination: root_destination.join(subdirectory),
+						show,
+					}
+				})
+				.collect()
+		} else {
+			let show: Show = serde_json::from_value(value)?;
+			let destination = show.destination().as_ref().map_or_else(
+				|| root_destination.to_path_buf(),
+				|subdirectory| root_destination.join(subdirectory),
+			);
+
+			vec![LibraryEntry { show, destination }]
+		};
+
+		Ok(Library { entries })
+	}
+
+	pub fn entries(&self) -> &[LibraryEntry] {
+		&self.entries
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::error::Error;
+
+	#[test]
+	fn test_single_show() -> Result<(), Box<dyn Error>> {
+		let json = r#"
+			{
+				"title": "Hard Pod",
+				"url": "https://example.com/hardpod.xml"
+			}
+			"#;
+
+		let library = Library::from_value(serde_json::from_str(json)?, Path::new("/archive"))?;
+		assert_eq!(library.entries().len(), 1);
+		assert_eq!(library.entries()[0].show().title(), "Hard Pod");
+		assert_eq!(library.entries()[0].destination(), Path::new("/archive"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_multiple_shows() -> Result<(), Box<dyn Error>> {
+		let json = r#"
+			{
+				"shows": [
+					{
+						"title": "Hard Pod",
+						"url": "https://example.com/hardpod.xml",
+						"destination": "hard"
+					},
+					{
+						"title": "Soft/Pod",
+						"url": "https://example.com/softpod.xml",
+						"stripWholeTitle": true
+					}
+				]
+			}
+			"#;
+
+		let library = Library::from_value(serde_json::from_str(json)?, Path::new("/archive"))?;
+		let entries = library.entries();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].show().title(), "Hard Pod");
+		assert_eq!(entries[0].destination(), Path::new("/archive/hard"));
+		assert_eq!(entries[1].show().title(), "Soft/Pod");
+		assert_eq!(entries[1].destination(), Path::new("/archive/Soft-Pod"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_bad_show_in_library() -> Result<(), Box<dyn Error>> {
+		let json = r#"
+			{
+				"shows": [
+					{
+						"title": "Hard Pod",
+						"url": "https://example.com/hardpod.xml",
+						"unknownOption": true
+					}
+				]
+			}
+			"#;
+
+		assert!(Library::from_value(serde_json::from_str(json)?, Path::new("/archive")).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_library_time_zone() -> Result<(), Box<dyn Error>> {
+		let json = r#"
+			{
+				"timezone": "America/New_York",
+				"shows": [
+					{
+						"title": "Hard Pod",
+						"url": "https://example.com/hardpod.xml"
+					},
+					{
+						"title": "Soft Pod",
+						"url": "https://example.com/softpod.xml",
+						"timezone": "+09:00"
+					}
+				]
+			}
+			"#;
+
+		let library = Library::from_value(serde_json::from_str(json)?, Path::new("/archive"))?;
+		let entries = library.entries();
+		assert_eq!(
+			entries[0].show().time_zone(),
+			Some(ArchiveTimeZone::parse("America/New_York")?)
+		);
+		assert_eq!(
+			entries[1].show().time_zone(),
+			Some(ArchiveTimeZone::parse("+09:00")?)
+		);
+
+		Ok(())
+	}
+}
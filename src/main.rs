@@ -18,31 +18,163 @@ mod download;
 mod feed;
 mod filesystem;
 mod helpers;
+mod interrupt;
+mod library;
+mod manifest;
+mod media;
+mod secrets;
 
 use clap::Parser;
 
 fn do_work() -> Result<(), Box<dyn std::error::Error>> {
 	let config = config::Config::parse();
-	let show: feed::Show = {
-		let config_file_path_string = config.config_file_path().to_string_lossy();
-		let config_file_handle =
-			filesystem::FilesystemError::handling_io_error_in(config_file_path_string, || {
-				std::fs::File::open(config.config_file_path())
-			})?;
+	interrupt::install()?;
 
-		serde_json::from_reader(config_file_handle)
-	}?;
+	// Pretending doesn't create the destination, and there's nothing to protect if it doesn't exist
+	if config.may_download() {
+		filesystem::create_directory(config.destination())?;
+	}
+	let _lock = config
+		.destination()
+		.is_dir()
+		.then(|| filesystem::DestinationLock::acquire(config.destination(), config.wait_for_lock()))
+		.transpose()?;
+	let library = library::Library::from_path(config.config_file_path(), config.destination())?;
+	let secrets = secrets::Secrets::load(config.secrets_file())?;
+	let context = download::DownloadContext::new(
+		download::HttpClient::new(&config.client_options())?,
+		config.retry_policy(),
+		config.rate_limiter(),
+		download::ByteBudget::new(config.max_bytes()),
+	);
+
+	match config.command() {
+		Some(config::Command::Verify { .. }) => verify(&library, &context, &secrets, &config),
+		None => sync(&library, &context, &secrets, &config),
+	}
+}
+
+fn sync(
+	library: &library::Library,
+	context: &download::DownloadContext,
+	secrets: &secrets::Secrets,
+	config: &config::Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut run_summary = helpers::RunSummary::new(!config.may_download());
+	for entry in library.entries() {
+		if interrupt::interrupted() {
+			break;
+		}
+
+		let result = helpers::sync_show(entry, context, secrets, config);
+		if let Err(e) = &result {
+			eprintln!("Error: {}: {}", entry.show().title(), e);
+		}
+		run_summary.record(entry.show().title(), result);
+	}
 
-	let (reader, _) = download::download_to_reader(show.url())?;
-	let episodes = feed::episodes_from_reader(reader, &show)?;
-	let classified_eps = helpers::classified_episodes(&show, &episodes, &config)?;
+	print!("{}", run_summary);
+
+	if interrupt::interrupted() {
+		return Err("interrupted (partial downloads were kept, and will resume next time)".into());
+	}
+
+	if run_summary.has_failures() {
+		let mut failures = Vec::new();
+		match run_summary.failed_show_count() {
+			0 => (),
+			shows => failures.push(format!("{} of {} shows", shows, library.entries().len())),
+		}
+		match run_summary.failed_episode_count() {
+			0 => (),
+			1 => failures.push(String::from("1 episode")),
+			episodes => failures.push(format!("{} episodes", episodes)),
+		}
+
+		return Err(format!("{} failed", failures.join(" and ")).into());
+	}
 
-	helpers::process_classified_episodes(classified_eps, &config)?;
 	Ok(())
 }
 
+fn verify(
+	library: &library::Library,
+	context: &download::DownloadContext,
+	secrets: &secrets::Secrets,
+	config: &config::Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut failed_shows = 0;
+	let mut checked = 0;
+	let mut problems = 0;
+	let mut requeued = 0;
+	for entry in library.entries() {
+		if interrupt::interrupted() {
+			break;
+		}
+
+		match helpers::verify_show(entry, context, secrets, config) {
+			Ok(verification) => {
+				checked += verification.checked;
+				problems += verification.problems.len();
+				requeued += verification.requeued;
+			}
+			Err(e) => {
+				eprintln!("Error: {}: {}", entry.show().title(), e);
+				failed_shows += 1;
+			}
+		}
+	}
+
+	let requeued_note = if requeued > 0 {
+		format!(" ({} re-queued for download)", requeued)
+	} else {
+		String::new()
+	};
+	println!(
+		"Checked {} files: {} with problems{}",
+		checked, problems, requeued_note
+	);
+
+	if interrupt::interrupted() {
+		return Err("interrupted".into());
+	}
+	if failed_shows > 0 {
+		return Err(format!(
+			"{} of {} shows couldn't be verified",
+			failed_shows,
+			library.entries().len()
+		)
+		.into());
+	}
+	if problems > 0 && !config.requeue() {
+		return Err("some episodes need downloading again (use verify --requeue)".into());
+	}
+	if problems > requeued {
+		return Err(
+			"some files aren't in their feeds any more, so they couldn't be re-queued".into(),
+		);
+	}
+
+	Ok(())
+}
+
+/// 130 for an interrupted run, like a shell would, and EX_TEMPFAIL if another run had the destination
+fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+	if interrupt::interrupted() {
+		130
+	} else if matches!(
+		error.downcast_ref(),
+		Some(filesystem::FilesystemError::Locked { .. })
+	) {
+		75
+	} else {
+		1
+	}
+}
+
 fn main() {
 	if let Err(e) = do_work() {
 		eprintln!("Error: {}", e);
+		std::process::exit(exit_code(&*e));
 	}
 }
@@ -0,0 +1,223 @@
+use super::ManifestError;
+use crate::feed::Episode;
+use crate::filesystem::{self, FilesystemError};
+use chrono::{DateTime, Utc};
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILENAME: &str = ".arcast-manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Getters, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct ManifestRecord {
+	guid: Option<String>,
+	enclosure_url: String,
+	filename: String,
+	size: u64,
+	downloaded_at: DateTime<Utc>,
+
+	/// Found already in the destination rather than downloaded, so `size` is just what the file
+	/// was then, not what it should be
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	adopted: bool,
+}
+
+/// A record of every episode archived into a destination, so we can recognize them
+/// by GUID even after their filenames would've changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+	records: Vec<ManifestRecord>,
+
+	#[serde(skip)]
+	known_guids: HashSet<String>,
+}
+
+impl Manifest {
+	pub fn path_in(destination: &Path) -> PathBuf {
+		destination.join(MANIFEST_FILENAME)
+	}
+
+	/// Loads the manifest in `destination`, or an empty one if there isn't one yet
+	pub fn load(destination: &Path) -> Result<Self, ManifestError> {
+		let path = Self::path_in(destination);
+		let path_string = path.to_string_lossy();
+
+		let file_handle = match std::fs::File::open(&path) {
+			Ok(handle) => handle,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+			Err(e) => return Err(FilesystemError::from_io_error(e, path_string).into()),
+		};
+
+		let mut manifest: Self =
+			serde_json::from_reader(BufReader::new(file_handle)).map_err(|source| {
+				ManifestError::Parsing {
+					source,
+					path: path_string.into_owned(),
+				}
+			})?;
+
+		manifest.known_guids = manifest
+			.records
+			.iter()
+			.filter_map(|r| r.guid.clone())
+			.collect();
+
+		Ok(manifest)
+	}
+
+	/// Writes to a temporary file first so a crash can't leave a half-written manifest
+	pub fn save(&self, destination: &Path) -> Result<(), ManifestError> {
+		filesystem::write_atomically(Self::path_in(destination), |writer| {
+			Ok(serde_json::to_writer_pretty(writer, self)?)
+		})?;
+
+		Ok(())
+	}
+
+	pub fn known_guids(&self) -> &HashSet<String> {
+		&self.known_guids
+	}
+
+	pub fn contains(&self, episode: &Episode) -> bool {
+		episode.guid().as_ref().map_or_else(
+			|| {
+				self.records
+					.iter()
+					.any(|r| &r.filename == episode.filename())
+			},
+			|guid| self.known_guids.contains(guid),
+		)
+	}
+
+	fn is_record_of(record: &ManifestRecord, episode: &Episode) -> bool {
+		episode.guid().as_ref().map_or_else(
+			|| &record.filename == episode.filename(),
+			|guid| record.guid.as_ref() == Some(guid),
+		)
+	}
+
+	/// The name `episode` was archived under, which may not be what it would be called today
+	pub fn filename_of(&self, episode: &Episode) -> Option<&str> {
+		self.records
+			.iter()
+			.rev()
+			.find(|r| Self::is_record_of(r, episode))
+			.map(|r| &r.filename[..])
+	}
+
+	/// The latest record of a file archived as `filename`
+	pub fn record_named(&self, filename: &str) -> Option<&ManifestRecord> {
+		self.records.iter().rev().find(|r| r.filename == filename)
+	}
+
+	/// Drops every record of `episode`, so it'll be downloaded again
+	pub fn forget(&mut self, episode: &Episode) {
+		self.records.retain(|r| !Self::is_record_of(r, episode));
+		if let Some(guid) = episode.guid() {
+			self.known_guids.remove(guid);
+		}
+	}
+
+	/// Records a download of `size` bytes
+	pub fn record(&mut self, episode: &Episode, size: u64) {
+		self.push(episode, size, false);
+	}
+
+	/// Records a file that was already there, which might not be complete
+	pub fn adopt(&mut self, episode: &Episode, size: u64) {
+		self.push(episode, size, true);
+	}
+
+	fn push(&mut self, episode: &Episode, size: u64, adopted: bool) {
+		if let Some(guid) = episode.guid() {
+			self.known_guids.insert(guid.clone());
+		}
+
+		self.records.push(ManifestRecord {
+			guid: episode.guid().clone(),
+			enclosure_url: episode.enclosure_url().clone(),
+			filename: episode.filename().clone(),
+			size,
+			downloaded_at: Utc::now(),
+			adopted,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::feed::EpisodeBuilder;
+	use chrono::NaiveDate;
+
+	fn new_episode(guid: Option<&str>, filename: &str) -> Episode {
+		EpisodeBuilder::default()
+			.enclosure_url("https://example.com/file.mp3")
+			.filename(filename)
+			.guid(guid.map(String::from))
+			.episode_name_range(0..filename.len())
+			.pub_date(NaiveDate::from_ymd_opt(2021, 2, 21).unwrap())
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn test_contains() {
+		let mut manifest = Manifest::default();
+		manifest.record(&new_episode(Some("guid-1"), "Old Name.mp3"), 10);
+		manifest.record(&new_episode(None, "No GUID.mp3"), 10);
+
+		assert!(manifest.contains(&new_episode(Some("guid-1"), "New Name.mp3")));
+		assert!(!manifest.contains(&new_episode(Some("guid-2"), "Old Name.mp3")));
+		assert!(manifest.contains(&new_episode(None, "No GUID.mp3")));
+		assert!(!manifest.contains(&new_episode(None, "Other.mp3")));
+	}
+
+	#[test]
+	fn test_filename_of_and_forget() {
+		let renamed = new_episode(Some("guid-1"), "New Name.mp3");
+		let no_guid = new_episode(None, "No GUID.mp3");
+
+		let mut manifest = Manifest::default();
+		manifest.record(&new_episode(Some("guid-1"), "Old Name.mp3"), 10);
+		manifest.record(&no_guid, 10);
+
+		assert_eq!(manifest.filename_of(&renamed), Some("Old Name.mp3"));
+		assert_eq!(manifest.filename_of(&no_guid), Some("No GUID.mp3"));
+
+		manifest.forget(&renamed);
+		assert!(!manifest.contains(&renamed));
+		assert_eq!(manifest.filename_of(&renamed), None);
+		assert!(manifest.contains(&no_guid));
+
+		manifest.forget(&no_guid);
+		assert!(manifest.records.is_empty());
+	}
+
+	#[test]
+	fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+		let destination =
+			std::env::temp_dir().join(format!("arcast-manifest-{}", std::process::id()));
+		std::fs::create_dir_all(&destination)?;
+
+		assert!(Manifest::load(&destination)?.records.is_empty());
+
+		let mut manifest = Manifest::default();
+		manifest.record(&new_episode(Some("guid-1"), "Name.mp3"), 1234);
+		manifest.adopt(&new_episode(Some("guid-2"), "Older.mp3"), 15);
+		manifest.save(&destination)?;
+
+		let loaded = Manifest::load(&destination)?;
+		assert_eq!(loaded.records, manifest.records);
+		assert!(loaded.known_guids().contains("guid-1"));
+		assert!(!loaded.record_named("Name.mp3").unwrap().adopted());
+		assert!(loaded.record_named("Older.mp3").unwrap().adopted());
+
+		std::fs::remove_dir_all(&destination)?;
+		Ok(())
+	}
+}
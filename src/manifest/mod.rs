@@ -0,0 +1,6 @@
+mod error;
+pub use error::*;
+
+#[allow(clippy::module_inception)]
+mod manifest;
+pub use manifest::*;
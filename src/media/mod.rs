@@ -0,0 +1,2 @@
+mod sniff;
+pub use sniff::*;
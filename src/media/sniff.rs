@@ -0,0 +1,146 @@
+use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
+
+/// How many bytes from the start of a file `MediaKind::sniff` wants to see
+pub const SNIFF_LENGTH: usize = 512;
+
+/// What a file's first few bytes say it is, regardless of its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+	Mpeg,
+	Mp4,
+	Ogg,
+	Flac,
+	Wav,
+	Matroska,
+	Markup,
+	Empty,
+	Unknown,
+}
+
+impl MediaKind {
+	pub fn sniff(bytes: &[u8]) -> Self {
+		let starts_with = |magic: &[u8]| bytes.starts_with(magic);
+
+		if starts_with(b"ID3") || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0)
+		{
+			// An ID3 tag, or straight into an MPEG audio (or ADTS AAC) frame
+			Self::Mpeg
+		} else if bytes.get(4..8) == Some(b"ftyp") {
+			Self::Mp4
+		} else if starts_with(b"OggS") {
+			Self::Ogg
+		} else if starts_with(b"fLaC") {
+			Self::Flac
+		} else if starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+			Self::Wav
+		} else if starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+			Self::Matroska
+		} else if looks_like_markup(bytes) {
+			Self::Markup
+		} else if bytes.is_empty() {
+			Self::Empty
+		} else {
+			Self::Unknown
+		}
+	}
+
+	/// Sniffs the start of the file at `path`
+	pub fn of_file(path: &Path) -> std::io::Result<Self> {
+		let mut start = Vec::with_capacity(SNIFF_LENGTH);
+		std::fs::File::open(path)?
+			.take(SNIFF_LENGTH as u64)
+			.read_to_end(&mut start)?;
+
+		Ok(Self::sniff(&start))
+	}
+
+	/// Whether a response that starts like this is worth archiving. We trust the bytes over the
+	/// `Content-Type` when they're recognizable, since plenty of hosts send audio as text/plain
+	pub fn accepts_download(self, content_type: Option<&str>) -> bool {
+		match self {
+			Self::Markup | Self::Empty => false,
+			Self::Unknown => !content_type.is_some_and(is_textual),
+			_ => true,
+		}
+	}
+}
+
+fn is_textual(content_type: &str) -> bool {
+	let mime = content_type
+		.split(';')
+		.next()
+		.unwrap_or_default()
+		.trim()
+		.to_ascii_lowercase();
+
+	mime.starts_with("text/")
+		|| mime.ends_with("/xml")
+		|| mime.ends_with("+xml")
+		|| mime.ends_with("/json")
+}
+
+/// Error pages and login forms, mostly
+fn looks_like_markup(bytes: &[u8]) -> bool {
+	let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+	bytes
+		.iter()
+		.find(|b| !b.is_ascii_whitespace())
+		.is_some_and(|&b| b == b'<')
+}
+
+impl Display for MediaKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::Mpeg => "MPEG audio",
+			Self::Mp4 => "MP4",
+			Self::Ogg => "Ogg",
+			Self::Flac => "FLAC",
+			Self::Wav => "WAV",
+			Self::Matroska => "Matroska",
+			Self::Markup => "an HTML or XML page",
+			Self::Empty => "nothing at all",
+			Self::Unknown => "unrecognized data",
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sniff() {
+		assert_eq!(MediaKind::sniff(b"ID3\x04\x00\x00"), MediaKind::Mpeg);
+		assert_eq!(MediaKind::sniff(&[0xFF, 0xFB, 0x90, 0x64]), MediaKind::Mpeg);
+		assert_eq!(
+			MediaKind::sniff(b"\x00\x00\x00\x20ftypM4A \x00\x00"),
+			MediaKind::Mp4
+		);
+		assert_eq!(MediaKind::sniff(b"OggS\x00\x02"), MediaKind::Ogg);
+		assert_eq!(MediaKind::sniff(b"fLaC\x00"), MediaKind::Flac);
+		assert_eq!(
+			MediaKind::sniff(b"RIFF\x24\x08\x00\x00WAVEfmt "),
+			MediaKind::Wav
+		);
+		assert_eq!(
+			MediaKind::sniff(b"\xEF\xBB\xBF\n  <!DOCTYPE html><html>"),
+			MediaKind::Markup
+		);
+		assert_eq!(MediaKind::sniff(b""), MediaKind::Empty);
+		assert_eq!(MediaKind::sniff(b"Access denied"), MediaKind::Unknown);
+	}
+
+	#[test]
+	fn test_accepts_download() {
+		assert!(MediaKind::Mpeg.accepts_download(Some("audio/mpeg")));
+		assert!(MediaKind::Mpeg.accepts_download(Some("text/plain")));
+		assert!(MediaKind::Unknown.accepts_download(Some("audio/x-weird")));
+		assert!(MediaKind::Unknown.accepts_download(None));
+		assert!(!MediaKind::Unknown.accepts_download(Some("text/html; charset=utf-8")));
+		assert!(!MediaKind::Unknown.accepts_download(Some("application/json")));
+		assert!(!MediaKind::Markup.accepts_download(Some("audio/mpeg")));
+		assert!(!MediaKind::Empty.accepts_download(None));
+	}
+}
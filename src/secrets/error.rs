@@ -0,0 +1,20 @@
+use crate::filesystem::FilesystemError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+	#[error(transparent)]
+	Filesystem(#[from] FilesystemError),
+
+	#[error("{path}: {source}")]
+	Parsing {
+		source: serde_json::Error,
+		path: String,
+	},
+
+	#[error("environment variable {0} isn't set")]
+	MissingEnvironmentVariable(String),
+
+	#[error("secret '{0}' isn't in the secrets file")]
+	MissingSecret(String),
+}
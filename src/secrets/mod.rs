@@ -0,0 +1,6 @@
+mod error;
+pub use error::*;
+
+#[allow(clippy::module_inception)]
+mod secrets;
+pub use secrets::*;
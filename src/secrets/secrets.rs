@@ -0,0 +1,91 @@
+use super::SecretError;
+use crate::filesystem::FilesystemError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A configuration value that can be kept out of the show JSON
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum SecretValue {
+	Literal(String),
+	Environment { env: String },
+	Secret { secret: String },
+}
+
+impl SecretValue {
+	pub fn resolve(&self, secrets: &Secrets) -> Result<String, SecretError> {
+		match self {
+			Self::Literal(value) => Ok(value.clone()),
+			Self::Environment { env } => {
+				std::env::var(env).map_err(|_| SecretError::MissingEnvironmentVariable(env.clone()))
+			}
+			Self::Secret { secret } => secrets
+				.values
+				.get(secret)
+				.cloned()
+				.ok_or_else(|| SecretError::MissingSecret(secret.clone())),
+		}
+	}
+}
+
+/// The contents of the secrets file: a JSON object of names to values
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secrets {
+	values: HashMap<String, String>,
+}
+
+impl Secrets {
+	pub fn load(path: Option<&Path>) -> Result<Self, SecretError> {
+		let Some(path) = path else {
+			return Ok(Self::default());
+		};
+
+		let path_string = path.to_string_lossy();
+		let file_handle = FilesystemError::handling_io_error_in(path_string.clone(), || {
+			std::fs::File::open(path)
+		})?;
+
+		serde_json::from_reader(std::io::BufReader::new(file_handle)).map_err(|source| {
+			SecretError::Parsing {
+				source,
+				path: path_string.into_owned(),
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_resolve() -> Result<(), Box<dyn std::error::Error>> {
+		let secrets: Secrets = serde_json::from_str(r#"{ "patreon": "hunter2" }"#)?;
+		std::env::set_var("ARCAST_TEST_SECRET", "swordfish");
+
+		let literal: SecretValue = serde_json::from_str(r#""plain""#)?;
+		let env: SecretValue = serde_json::from_str(r#"{ "env": "ARCAST_TEST_SECRET" }"#)?;
+		let secret: SecretValue = serde_json::from_str(r#"{ "secret": "patreon" }"#)?;
+
+		assert_eq!(literal.resolve(&secrets)?, "plain");
+		assert_eq!(env.resolve(&secrets)?, "swordfish");
+		assert_eq!(secret.resolve(&secrets)?, "hunter2");
+
+		let missing_env: SecretValue =
+			serde_json::from_str(r#"{ "env": "ARCAST_TEST_SECRET_THAT_ISNT_SET" }"#)?;
+		let missing_secret: SecretValue = serde_json::from_str(r#"{ "secret": "supercast" }"#)?;
+
+		assert!(matches!(
+			missing_env.resolve(&secrets),
+			Err(SecretError::MissingEnvironmentVariable(_))
+		));
+		assert!(matches!(
+			missing_secret.resolve(&secrets),
+			Err(SecretError::MissingSecret(_))
+		));
+
+		Ok(())
+	}
+}